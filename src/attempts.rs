@@ -0,0 +1,191 @@
+use std::{
+  future::Future,
+  pin::Pin,
+  sync::{Arc, Mutex},
+  task::{Context, Poll},
+  time::Instant,
+};
+
+use aws_smithy_client::{
+  conns, erase::DynConnector, http_connector::HttpConnector, hyper_ext::Adapter,
+};
+use aws_smithy_http::{body::SdkBody, result::ConnectorError};
+use hyper::{Request, Response};
+use serde_json::{Number, Value};
+use tower::Service;
+
+use crate::models::{DynamoDbSimulationMetrics, RequestAttempt};
+
+tokio::task_local! {
+  /// The attempts made for the DynamoDB request currently being sent by this task
+  static ATTEMPTS: Arc<Mutex<Vec<Attempt>>>;
+}
+
+/// A single attempt, as recorded by the connector
+struct Attempt {
+  latency: Number,
+  status: Option<u16>,
+  failure_reason: Option<String>,
+  /// Whether the attempt was dropped before it finished
+  abandoned: bool,
+}
+
+/// The default HTTP connector, wrapped so that every attempt the AWS SDK makes (including its hidden retries) is timed
+/// and recorded against the DynamoDB request that's being sent with `record_attempts`
+pub fn recording_connector() -> HttpConnector {
+  HttpConnector::ConnectorFn(Arc::new(|settings, sleep| {
+    let mut builder = Adapter::builder().connector_settings(settings.clone());
+    if let Some(sleep) = sleep {
+      builder = builder.sleep_impl(sleep);
+    }
+
+    Some(DynConnector::new(AttemptRecorder {
+      inner: builder.build(conns::https()),
+    }))
+  }))
+}
+
+/// Sends a DynamoDB request, adding every attempt the AWS SDK made to send it to the simulation's metrics
+pub async fn record_attempts<F: Future>(
+  request: &'static str,
+  metrics: &mut DynamoDbSimulationMetrics,
+  future: F,
+) -> F::Output {
+  let attempts = Arc::new(Mutex::new(Vec::new()));
+  let output = ATTEMPTS.scope(attempts.clone(), future).await;
+
+  // An attempt is dropped before it finishes either when the SDK gives up on it because it timed out, or when its simulation
+  // is cancelled. A cancelled simulation drops this future along with the attempt, so any abandoned attempt that's still
+  // recorded here was timed out by the SDK
+  let attempts = attempts.lock().unwrap();
+  for (i, attempt) in attempts.iter().enumerate() {
+    metrics.sdk_attempts += 1;
+    if i > 0 {
+      metrics.sdk_retries += 1;
+    }
+    metrics.request_attempts.push(RequestAttempt {
+      request,
+      attempt: i as u32 + 1,
+      latency: attempt.latency.clone(),
+      status: attempt.status,
+      failure_reason: if attempt.abandoned {
+        Some(String::from("timeout"))
+      } else {
+        attempt.failure_reason.clone()
+      },
+    });
+  }
+
+  output
+}
+
+#[derive(Clone)]
+struct AttemptRecorder<C> {
+  inner: C,
+}
+
+impl<C> Service<Request<SdkBody>> for AttemptRecorder<C>
+where
+  C: Service<Request<SdkBody>, Response = Response<SdkBody>, Error = ConnectorError>,
+  C::Future: Send + 'static,
+{
+  type Response = Response<SdkBody>;
+  type Error = ConnectorError;
+  type Future = Pin<Box<dyn Future<Output = Result<Response<SdkBody>, ConnectorError>> + Send>>;
+
+  fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), ConnectorError>> {
+    self.inner.poll_ready(cx)
+  }
+
+  fn call(&mut self, request: Request<SdkBody>) -> Self::Future {
+    // Requests sent outside of `record_attempts` (e.g. while seeding or cleaning up) aren't recorded
+    let guard = ATTEMPTS
+      .try_with(Arc::clone)
+      .ok()
+      .map(|attempts| AttemptGuard {
+        attempts,
+        started: Instant::now(),
+        finished: false,
+      });
+    let response = self.inner.call(request);
+
+    Box::pin(async move {
+      let result = response.await;
+      let Some(mut guard) = guard else {
+        return result;
+      };
+
+      match result {
+        Ok(response)
+          if response.status().is_client_error() || response.status().is_server_error() =>
+        {
+          // The body of an error response is small, and holds the error code that explains why the SDK retried
+          let (parts, body) = response.into_parts();
+          let body = match hyper::body::to_bytes(body).await {
+            Ok(body) => body,
+            Err(e) => {
+              guard.finish(Some(parts.status.as_u16()), Some("io".to_owned()));
+              return Err(ConnectorError::io(e));
+            }
+          };
+          let reason = error_code(&body).unwrap_or_else(|| format!("HTTP {}", parts.status));
+          guard.finish(Some(parts.status.as_u16()), Some(reason));
+          Ok(Response::from_parts(parts, SdkBody::from(body)))
+        }
+        Ok(response) => {
+          guard.finish(Some(response.status().as_u16()), None);
+          Ok(response)
+        }
+        Err(e) => {
+          let reason = if e.is_timeout() {
+            "timeout"
+          } else if e.is_io() {
+            "io"
+          } else {
+            "connectorError"
+          };
+          guard.finish(None, Some(reason.to_owned()));
+          Err(e)
+        }
+      }
+    })
+  }
+}
+
+/// Records an attempt when it finishes, or as abandoned if it's dropped before it does
+struct AttemptGuard {
+  attempts: Arc<Mutex<Vec<Attempt>>>,
+  started: Instant,
+  finished: bool,
+}
+
+impl AttemptGuard {
+  fn finish(&mut self, status: Option<u16>, failure_reason: Option<String>) {
+    self.record(status, failure_reason, false);
+  }
+
+  fn record(&mut self, status: Option<u16>, failure_reason: Option<String>, abandoned: bool) {
+    self.finished = true;
+    self.attempts.lock().unwrap().push(Attempt {
+      latency: Number::from(self.started.elapsed().as_micros()),
+      status,
+      failure_reason,
+      abandoned,
+    });
+  }
+}
+
+impl Drop for AttemptGuard {
+  fn drop(&mut self) {
+    if !self.finished {
+      self.record(None, None, true);
+    }
+  }
+}
+
+/// The error code DynamoDB returns in the `__type` field of an error response, e.g. `ProvisionedThroughputExceededException`
+fn error_code(body: &[u8]) -> Option<String> {
+  let body: Value = serde_json::from_slice(body).ok()?;
+  let error_type = body.get("__type")?.as_str()?;
+  error_type.rsplit('#').next().map(str::to_owned)
+}
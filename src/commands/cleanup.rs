@@ -0,0 +1,141 @@
+use std::{collections::HashMap, env, sync::Arc};
+
+use aws_sdk_dynamodb::{
+  types::{AttributeValue, DeleteRequest, WriteRequest},
+  Client,
+};
+use clap::{ArgGroup, Args};
+use log::info;
+use tokio::{sync::Semaphore, task::JoinSet};
+
+use crate::models::RUN_ID_ATTRIBUTE;
+
+/// The default number of BatchWriteItem requests to have in flight at once when deleting items
+pub const DEFAULT_DELETE_PARALLELISM: usize = 16;
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("runs").required(true).args(["run_id", "all_runs"])))]
+pub struct CleanupArgs {
+  /// The DynamoDB table to clean up
+  #[arg(short, long, default_value_t = format!("{}-high-velocity-table", env::var("USER").unwrap()))]
+  table_name: String,
+  /// A custom DynamoDB endpoint to use instead of the default AWS endpoint (e.g. http://localhost:8000 for DynamoDB Local)
+  #[arg(short, long)]
+  endpoint_url: Option<String>,
+  /// The ID of the run whose leftover items should be deleted
+  #[arg(short, long)]
+  run_id: Option<String>,
+  /// Delete the leftover items of every run. Do not use this while a benchmark is running against the table
+  #[arg(short, long)]
+  all_runs: bool,
+  /// The number of BatchWriteItem requests to have in flight at once
+  #[arg(short, long, default_value_t = DEFAULT_DELETE_PARALLELISM)]
+  parallelism: usize,
+}
+
+pub async fn run(args: CleanupArgs) -> anyhow::Result<()> {
+  let dynamodb_client = super::dynamodb_client(args.endpoint_url.as_deref()).await;
+  let (filter_expression, expression_attribute_values) = match &args.run_id {
+    Some(run_id) => {
+      info!(
+        "Finding items left behind by run {run_id} in {}...",
+        args.table_name
+      );
+      (
+        "#runId = :runId",
+        Some(HashMap::from([(
+          ":runId".to_owned(),
+          AttributeValue::S(run_id.clone()),
+        )])),
+      )
+    }
+    None => {
+      info!(
+        "Finding items left behind by any run in {}...",
+        args.table_name
+      );
+      ("attribute_exists(#runId)", None)
+    }
+  };
+
+  let mut items_deleted = 0;
+  let mut exclusive_start_key = None;
+  loop {
+    let response = dynamodb_client
+      .scan()
+      .table_name(&args.table_name)
+      .projection_expression("id")
+      .filter_expression(filter_expression)
+      .expression_attribute_names("#runId", RUN_ID_ATTRIBUTE)
+      .set_expression_attribute_values(expression_attribute_values.clone())
+      .set_exclusive_start_key(exclusive_start_key)
+      .send()
+      .await?;
+
+    let partition_keys = response
+      .items()
+      .unwrap_or_default()
+      .iter()
+      .filter_map(|item| item.get("id").and_then(|id| id.as_s().ok()).cloned())
+      .collect::<Vec<String>>();
+    items_deleted += delete_items(
+      &dynamodb_client,
+      &args.table_name,
+      partition_keys,
+      args.parallelism,
+    )
+    .await?;
+
+    exclusive_start_key = response.last_evaluated_key().cloned();
+    if exclusive_start_key.is_none() {
+      break;
+    }
+  }
+
+  info!(
+    "Deleted {items_deleted} leftover items from {}",
+    args.table_name
+  );
+
+  Ok(())
+}
+
+/// Deletes the items with the given partition keys using up to `parallelism` concurrent BatchWriteItem requests, returning the
+/// number of items deleted
+pub async fn delete_items(
+  dynamodb_client: &Client,
+  table_name: &str,
+  partition_keys: Vec<String>,
+  parallelism: usize,
+) -> anyhow::Result<usize> {
+  let permits = Arc::new(Semaphore::new(parallelism.max(1)));
+  let mut batch_deleters = JoinSet::new();
+  for batch in partition_keys.chunks(super::MAX_BATCH_WRITE_SIZE) {
+    let permit = permits.clone().acquire_owned().await?;
+    let dynamodb_client = dynamodb_client.clone();
+    let table_name = table_name.to_owned();
+    let write_requests = batch
+      .iter()
+      .map(|partition_key| {
+        WriteRequest::builder()
+          .delete_request(
+            DeleteRequest::builder()
+              .key("id", AttributeValue::S(partition_key.clone()))
+              .build(),
+          )
+          .build()
+      })
+      .collect();
+
+    batch_deleters.spawn(async move {
+      let _permit = permit;
+      super::batch_write_with_retry(&dynamodb_client, &table_name, write_requests, |_| ()).await
+    });
+  }
+
+  while let Some(result) = batch_deleters.join_next().await {
+    result??;
+  }
+
+  Ok(partition_keys.len())
+}
@@ -0,0 +1,672 @@
+use std::{collections::BTreeMap, fmt, fs, path::PathBuf};
+
+use anyhow::anyhow;
+use clap::Args;
+use log::info;
+use serde::Serialize;
+
+use crate::{
+  histograms::{LatencyHistograms, LatencyPercentiles, Phase},
+  models::DynamoOperation,
+};
+
+use super::recorded::{load_run, ElasticsearchArgs, RecordedMetrics, RunTotals};
+
+#[derive(Args)]
+pub struct CompareArgs {
+  /// The baseline run: a metrics file written by the json-lines, csv or parquet sink, or the ID of a run published to Elasticsearch
+  baseline: String,
+  /// The candidate run to compare against the baseline, in the same form as the baseline
+  candidate: String,
+  /// The significance level that a change in a latency percentile must reach to count as a regression
+  #[arg(long, default_value_t = 0.05)]
+  alpha: f64,
+  /// The percentage by which the candidate's p50 latency may increase before it's considered a regression
+  #[arg(long, default_value_t = 10.0)]
+  p50_threshold: f64,
+  /// The percentage by which the candidate's p99 latency may increase before it's considered a regression
+  #[arg(long, default_value_t = 10.0)]
+  p99_threshold: f64,
+  /// The percentage by which the candidate's throughput may decrease before it's considered a regression
+  #[arg(long, default_value_t = 10.0)]
+  throughput_threshold: f64,
+  #[command(flatten)]
+  elasticsearch: ElasticsearchArgs,
+  /// Write the comparison to this file as JSON
+  #[arg(long)]
+  output_file: Option<PathBuf>,
+}
+
+/// Everything a run recorded, read back from wherever its metrics were published
+#[derive(Default)]
+struct RunResults {
+  totals: RunTotals,
+  latency_histograms: LatencyHistograms,
+  /// Every latency, in microseconds, for the significance tests
+  latencies: BTreeMap<(DynamoOperation, Phase), Vec<f64>>,
+}
+
+impl RunResults {
+  fn record(&mut self, metrics: RecordedMetrics) {
+    self.totals.record(&metrics);
+    for (phase, latency) in metrics.latencies() {
+      self
+        .latency_histograms
+        .record(metrics.operation, phase, latency as u64);
+      self
+        .latencies
+        .entry((metrics.operation, phase))
+        .or_default()
+        .push(latency);
+    }
+  }
+}
+
+/// A value measured in both runs
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct Delta {
+  pub baseline: f64,
+  pub candidate: f64,
+  /// The change from the baseline to the candidate, as a percentage of the baseline. Absent when the baseline is 0 but the
+  /// candidate isn't, since the change is then infinite (e.g. errors that are new in the candidate)
+  pub change_percent: Option<f64>,
+}
+
+impl Delta {
+  fn new(baseline: f64, candidate: f64) -> Delta {
+    let change_percent = if baseline != 0.0 {
+      Some((candidate - baseline) / baseline * 100.0)
+    } else if candidate == 0.0 {
+      Some(0.0)
+    } else {
+      None
+    };
+
+    Delta {
+      baseline,
+      candidate,
+      change_percent,
+    }
+  }
+
+  /// Whether the value increased by more than the given percentage
+  fn increased_by_more_than(&self, threshold_percent: f64) -> bool {
+    self
+      .change_percent
+      .is_none_or(|change_percent| change_percent > threshold_percent)
+  }
+
+  /// Whether the value decreased by more than the given percentage
+  fn decreased_by_more_than(&self, threshold_percent: f64) -> bool {
+    self
+      .change_percent
+      .is_some_and(|change_percent| -change_percent > threshold_percent)
+  }
+}
+
+/// The run that an operation or phase was missing from
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Side {
+  Baseline,
+  Candidate,
+}
+
+/// An operation, or a phase of an operation, that only one of the runs recorded. The runs can't be compared there, so it
+/// counts as a regression when the candidate is missing it (e.g. a candidate that stopped producing an operation), but not
+/// when it's new in the candidate (e.g. the corrected latencies of a paced candidate)
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MissingComparison {
+  pub operation: DynamoOperation,
+  /// The missing phase, or absent if the whole operation is missing
+  pub phase: Option<Phase>,
+  pub missing_from: Side,
+  pub regressed: bool,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationComparison {
+  pub operation: DynamoOperation,
+  pub throughput_per_second: Delta,
+  pub error_rate: Delta,
+  pub regressed: bool,
+}
+
+/// The latency percentiles (in milliseconds) of a single phase of an operation in both runs
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyComparison {
+  pub operation: DynamoOperation,
+  pub phase: Phase,
+  pub baseline_count: u64,
+  pub candidate_count: u64,
+  pub p50: Delta,
+  pub p90: Delta,
+  pub p99: Delta,
+  pub p999: Delta,
+  /// The p-value of the change in the p50 latency
+  pub p50_p_value: f64,
+  /// The p-value of the change in the p99 latency
+  pub p99_p_value: f64,
+  pub regressed: bool,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Comparison {
+  pub baseline: String,
+  pub candidate: String,
+  pub alpha: f64,
+  pub operations: Vec<OperationComparison>,
+  pub latencies: Vec<LatencyComparison>,
+  pub missing: Vec<MissingComparison>,
+  pub regressions: usize,
+}
+
+pub async fn run(args: CompareArgs) -> anyhow::Result<()> {
+  let mut baseline = RunResults::default();
+  load_run(&args.elasticsearch, &args.baseline, |metrics| {
+    baseline.record(metrics)
+  })
+  .await?;
+  let mut candidate = RunResults::default();
+  load_run(&args.elasticsearch, &args.candidate, |metrics| {
+    candidate.record(metrics)
+  })
+  .await?;
+  let comparison = compare(&args, &baseline, &candidate);
+
+  println!("{comparison}");
+  if let Some(output_file) = &args.output_file {
+    fs::write(output_file, serde_json::to_string_pretty(&comparison)?)?;
+    info!("Wrote the comparison to {}", output_file.display());
+  }
+
+  if comparison.regressions > 0 {
+    Err(anyhow!(
+      "The candidate regressed in {} place(s) compared to the baseline",
+      comparison.regressions
+    ))
+  } else {
+    Ok(())
+  }
+}
+
+fn compare(args: &CompareArgs, baseline: &RunResults, candidate: &RunResults) -> Comparison {
+  let operations = baseline
+    .totals
+    .operations
+    .keys()
+    .filter(|operation| candidate.totals.operations.contains_key(operation))
+    .map(|&operation| {
+      let throughput_per_second = Delta::new(
+        baseline.totals.operation_throughput(operation),
+        candidate.totals.operation_throughput(operation),
+      );
+      OperationComparison {
+        operation,
+        throughput_per_second,
+        error_rate: Delta::new(
+          baseline.totals.error_rate(operation),
+          candidate.totals.error_rate(operation),
+        ),
+        regressed: throughput_per_second.decreased_by_more_than(args.throughput_threshold),
+      }
+    })
+    .collect::<Vec<OperationComparison>>();
+
+  let candidate_percentiles = candidate
+    .latency_histograms
+    .percentiles()
+    .into_iter()
+    .map(|percentiles| ((percentiles.operation, percentiles.phase), percentiles))
+    .collect::<BTreeMap<(DynamoOperation, Phase), LatencyPercentiles>>();
+  let latencies = baseline
+    .latency_histograms
+    .percentiles()
+    .into_iter()
+    .filter_map(|baseline_percentiles| {
+      let key = (baseline_percentiles.operation, baseline_percentiles.phase);
+      let candidate_percentiles = candidate_percentiles.get(&key)?;
+      // The percentiles are recorded in microseconds, but are far easier to read in milliseconds
+      let delta = |baseline: u64, candidate: u64| {
+        Delta::new(baseline as f64 / 1000.0, candidate as f64 / 1000.0)
+      };
+      let p50 = delta(baseline_percentiles.p50, candidate_percentiles.p50);
+      let p99 = delta(baseline_percentiles.p99, candidate_percentiles.p99);
+      let (baseline_latencies, candidate_latencies) =
+        (&baseline.latencies[&key], &candidate.latencies[&key]);
+      let p50_p_value = exceedance_p_value(
+        baseline_latencies,
+        candidate_latencies,
+        baseline_percentiles.p50 as f64,
+      );
+      let p99_p_value = exceedance_p_value(
+        baseline_latencies,
+        candidate_latencies,
+        baseline_percentiles.p99 as f64,
+      );
+
+      Some(LatencyComparison {
+        operation: key.0,
+        phase: key.1,
+        baseline_count: baseline_percentiles.count,
+        candidate_count: candidate_percentiles.count,
+        p50,
+        p90: delta(baseline_percentiles.p90, candidate_percentiles.p90),
+        p99,
+        p999: delta(baseline_percentiles.p999, candidate_percentiles.p999),
+        p50_p_value,
+        p99_p_value,
+        regressed: (p50.increased_by_more_than(args.p50_threshold) && p50_p_value < args.alpha)
+          || (p99.increased_by_more_than(args.p99_threshold) && p99_p_value < args.alpha),
+      })
+    })
+    .collect::<Vec<LatencyComparison>>();
+
+  let missing = missing_comparisons(baseline, candidate);
+
+  let regressions = operations
+    .iter()
+    .filter(|operation| operation.regressed)
+    .count()
+    + latencies.iter().filter(|latency| latency.regressed).count()
+    + missing.iter().filter(|missing| missing.regressed).count();
+
+  Comparison {
+    baseline: args.baseline.clone(),
+    candidate: args.candidate.clone(),
+    alpha: args.alpha,
+    operations,
+    latencies,
+    missing,
+    regressions,
+  }
+}
+
+/// Every operation that only one of the runs recorded, and every phase that only one of the runs recorded of the operations
+/// they both recorded
+fn missing_comparisons(baseline: &RunResults, candidate: &RunResults) -> Vec<MissingComparison> {
+  let mut missing = Vec::new();
+  for (present, absent, missing_from) in [
+    (baseline, candidate, Side::Candidate),
+    (candidate, baseline, Side::Baseline),
+  ] {
+    missing.extend(
+      present
+        .totals
+        .operations
+        .keys()
+        .filter(|operation| !absent.totals.operations.contains_key(operation))
+        .map(|&operation| MissingComparison {
+          operation,
+          phase: None,
+          missing_from,
+          regressed: missing_from == Side::Candidate,
+        }),
+    );
+    missing.extend(
+      present
+        .latencies
+        .keys()
+        .filter(|(operation, _)| absent.totals.operations.contains_key(operation))
+        .filter(|key| !absent.latencies.contains_key(key))
+        .map(|&(operation, phase)| MissingComparison {
+          operation,
+          phase: Some(phase),
+          missing_from,
+          regressed: missing_from == Side::Candidate,
+        }),
+    );
+  }
+
+  missing
+}
+
+/// The two-sided p-value of a two-proportion z-test on the fraction of each run's latencies that exceed the baseline's
+/// percentile. If the candidate's percentile really is higher, significantly more of its latencies exceed the baseline's
+/// percentile than the baseline's own do. Unlike a t-test, this doesn't assume the latencies are normally distributed, which
+/// they never are, and it tests the tail directly rather than the mean
+fn exceedance_p_value(baseline: &[f64], candidate: &[f64], percentile: f64) -> f64 {
+  let (n1, n2) = (baseline.len() as f64, candidate.len() as f64);
+  let exceeding = |latencies: &[f64]| {
+    latencies
+      .iter()
+      .filter(|&&latency| latency > percentile)
+      .count() as f64
+  };
+  let (x1, x2) = (exceeding(baseline), exceeding(candidate));
+
+  let pooled = (x1 + x2) / (n1 + n2);
+  let standard_error = (pooled * (1.0 - pooled) * (1.0 / n1 + 1.0 / n2)).sqrt();
+  if !standard_error.is_normal() {
+    return 1.0;
+  }
+
+  let z = (x2 / n2 - x1 / n1) / standard_error;
+  erfc(z.abs() / std::f64::consts::SQRT_2)
+}
+
+/// The complementary error function, accurate to within 1.2e-7 (Numerical Recipes' `erfcc`)
+fn erfc(x: f64) -> f64 {
+  let z = x.abs();
+  let t = 1.0 / (1.0 + 0.5 * z);
+  let result = t
+    * (-z * z - 1.26551223
+      + t
+        * (1.00002368
+          + t
+            * (0.37409196
+              + t
+                * (0.09678418
+                  + t
+                    * (-0.18628806
+                      + t
+                        * (0.27886807
+                          + t
+                            * (-1.13520398
+                              + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277)))))))))
+      .exp();
+
+  if x >= 0.0 {
+    result
+  } else {
+    2.0 - result
+  }
+}
+
+/// Shows the change as a percentage, or as new when the baseline was 0
+impl fmt::Display for Delta {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.change_percent {
+      Some(change_percent) => f.pad(&format!("{change_percent:+.1}%")),
+      None => f.pad("new"),
+    }
+  }
+}
+
+impl fmt::Display for Comparison {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "Baseline:  {}", self.baseline)?;
+    writeln!(f, "Candidate: {}", self.candidate)?;
+
+    writeln!(f)?;
+    writeln!(
+      f,
+      "{:<10} {:>12} {:>12} {:>9} {:>12} {:>12} {:>10}",
+      "Operation", "Baseline/s", "Candidate/s", "Change", "Base errors", "Cand errors", ""
+    )?;
+    for operation in &self.operations {
+      writeln!(
+        f,
+        "{:<10} {:>12.2} {:>12.2} {:>9} {:>11.2}% {:>11.2}% {:>10}",
+        format!("{:?}", operation.operation),
+        operation.throughput_per_second.baseline,
+        operation.throughput_per_second.candidate,
+        operation.throughput_per_second,
+        operation.error_rate.baseline * 100.0,
+        operation.error_rate.candidate * 100.0,
+        if operation.regressed { "REGRESSED" } else { "" }
+      )?;
+    }
+
+    writeln!(f)?;
+    writeln!(
+      f,
+      "{:<10} {:<20} {:>10} {:>10} {:>9} {:>8} {:>10} {:>10} {:>9} {:>8} {:>10}",
+      "Operation",
+      "Phase (ms)",
+      "Base p50",
+      "Cand p50",
+      "Change",
+      "p-value",
+      "Base p99",
+      "Cand p99",
+      "Change",
+      "p-value",
+      ""
+    )?;
+    for latency in &self.latencies {
+      writeln!(
+        f,
+        "{:<10} {:<20} {:>10.3} {:>10.3} {:>9} {:>8.4} {:>10.3} {:>10.3} {:>9} {:>8.4} {:>10}",
+        format!("{:?}", latency.operation),
+        format!("{:?}", latency.phase),
+        latency.p50.baseline,
+        latency.p50.candidate,
+        latency.p50,
+        latency.p50_p_value,
+        latency.p99.baseline,
+        latency.p99.candidate,
+        latency.p99,
+        latency.p99_p_value,
+        if latency.regressed { "REGRESSED" } else { "" }
+      )?;
+    }
+
+    if !self.missing.is_empty() {
+      writeln!(f)?;
+      for missing in &self.missing {
+        let (missing_from, outcome) = match missing.missing_from {
+          Side::Baseline => ("baseline", "new"),
+          Side::Candidate => ("candidate", "REGRESSED"),
+        };
+        match missing.phase {
+          Some(phase) => writeln!(
+            f,
+            "{:?} {phase:?} is missing from the {missing_from} ({outcome})",
+            missing.operation
+          )?,
+          None => writeln!(
+            f,
+            "{:?} is missing from the {missing_from} ({outcome})",
+            missing.operation
+          )?,
+        }
+      }
+    }
+
+    writeln!(f)?;
+    if self.regressions == 0 {
+      write!(f, "No significant regressions (alpha = {})", self.alpha)
+    } else {
+      write!(
+        f,
+        "{} significant regression(s) (alpha = {})",
+        self.regressions, self.alpha
+      )
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use clap::Parser;
+  use serde_json::json;
+
+  use super::*;
+
+  #[derive(Parser)]
+  struct TestCli {
+    #[command(flatten)]
+    args: CompareArgs,
+  }
+
+  fn assert_close(actual: f64, expected: f64) {
+    assert!(
+      (actual - expected).abs() < 1e-6,
+      "expected {expected}, but got {actual}"
+    );
+  }
+
+  fn args() -> CompareArgs {
+    TestCli::parse_from(["compare", "baseline.jsonl", "candidate.jsonl"]).args
+  }
+
+  fn run_results(metrics: &[(&str, bool, f64)]) -> RunResults {
+    let mut results = RunResults::default();
+    for (i, (operation, successful, simulation_time)) in metrics.iter().enumerate() {
+      results.record(
+        serde_json::from_value(json!({
+          "operation": operation,
+          "timestamp": format!("2026-01-01T00:00:{:02}Z", i % 60),
+          "successful": successful,
+          "simulationTime": simulation_time,
+        }))
+        .unwrap(),
+      );
+    }
+
+    results
+  }
+
+  #[test]
+  fn erfc_matches_known_values() {
+    assert_close(erfc(0.0), 1.0);
+    assert_close(erfc(0.5), 0.4795001);
+    assert_close(erfc(1.0), 0.1572992);
+    assert_close(erfc(2.0), 0.0046777);
+    assert_close(erfc(-1.0), 1.8427008);
+  }
+
+  #[test]
+  fn exceedance_p_value_matches_a_known_z_test() {
+    // 10% of the baseline and 30% of the candidate exceed the percentile: the pooled proportion is 0.2, so
+    // z = 0.2 / sqrt(0.2 * 0.8 * (1/100 + 1/100)) = 3.5355, and the two-sided p-value is erfc(2.5)
+    let baseline = (0..100)
+      .map(|i| if i < 10 { 200.0 } else { 50.0 })
+      .collect::<Vec<f64>>();
+    let candidate = (0..100)
+      .map(|i| if i < 30 { 200.0 } else { 50.0 })
+      .collect::<Vec<f64>>();
+
+    assert_close(exceedance_p_value(&baseline, &candidate, 100.0), 0.0004070);
+    assert_close(exceedance_p_value(&candidate, &baseline, 100.0), 0.0004070);
+  }
+
+  #[test]
+  fn exceedance_p_value_is_not_significant_for_identical_runs() {
+    let latencies = (1..=100).map(f64::from).collect::<Vec<f64>>();
+
+    assert_close(exceedance_p_value(&latencies, &latencies, 50.0), 1.0);
+  }
+
+  #[test]
+  fn exceedance_p_value_is_not_significant_without_data_or_variance() {
+    let latencies = [10.0, 20.0, 30.0];
+
+    assert_eq!(exceedance_p_value(&[], &[], 10.0), 1.0);
+    assert_eq!(exceedance_p_value(&latencies, &[], 10.0), 1.0);
+    assert_eq!(exceedance_p_value(&latencies, &latencies, 100.0), 1.0);
+    assert_eq!(exceedance_p_value(&latencies, &latencies, 0.0), 1.0);
+  }
+
+  #[test]
+  fn delta_reports_changes_from_a_zero_baseline_as_new() {
+    let new = Delta::new(0.0, 0.5);
+    assert_eq!(new.change_percent, None);
+    assert_eq!(new.to_string(), "new");
+    assert!(new.increased_by_more_than(10.0));
+    assert!(!new.decreased_by_more_than(10.0));
+
+    assert_eq!(Delta::new(0.0, 0.0).change_percent, Some(0.0));
+    assert_eq!(Delta::new(2.0, 3.0).change_percent, Some(50.0));
+    assert_eq!(Delta::new(2.0, 1.0).to_string(), "-50.0%");
+  }
+
+  #[test]
+  fn compare_counts_operations_missing_from_the_candidate_as_regressions() {
+    let baseline = run_results(&[
+      ("read", true, 1000.0),
+      ("write", true, 2000.0),
+      ("read", true, 1000.0),
+    ]);
+    let candidate = run_results(&[
+      ("read", true, 1000.0),
+      ("update", true, 3000.0),
+      ("read", true, 1000.0),
+    ]);
+
+    let comparison = compare(&args(), &baseline, &candidate);
+
+    let missing = comparison
+      .missing
+      .iter()
+      .map(|missing| (missing.operation, missing.phase, missing.missing_from))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      missing,
+      [
+        (DynamoOperation::Write, None, Side::Candidate),
+        (DynamoOperation::Update, None, Side::Baseline),
+      ]
+    );
+    assert_eq!(comparison.operations.len(), 1);
+    assert_eq!(comparison.regressions, 1);
+  }
+
+  #[test]
+  fn compare_counts_phases_missing_from_the_candidate_as_regressions() {
+    let mut baseline = run_results(&[("read", true, 1000.0)]);
+    baseline.record(
+      serde_json::from_value(json!({
+        "operation": "read",
+        "timestamp": "2026-01-01T00:00:01Z",
+        "successful": true,
+        "simulationTime": 1000.0,
+        "readTime": 900.0,
+      }))
+      .unwrap(),
+    );
+    let candidate = run_results(&[("read", true, 1000.0), ("read", true, 1000.0)]);
+
+    let comparison = compare(&args(), &baseline, &candidate);
+
+    assert_eq!(comparison.missing.len(), 1);
+    assert_eq!(comparison.missing[0].phase, Some(Phase::Read));
+    assert_eq!(comparison.missing[0].missing_from, Side::Candidate);
+    assert_eq!(comparison.regressions, 1);
+  }
+
+  #[test]
+  fn compare_reports_phases_new_in_the_candidate_without_failing() {
+    let baseline = run_results(&[("read", true, 1000.0)]);
+    let mut candidate = run_results(&[("read", true, 1000.0)]);
+    candidate.record(
+      serde_json::from_value(json!({
+        "operation": "read",
+        "timestamp": "2026-01-01T00:00:01Z",
+        "successful": true,
+        "simulationTime": 1000.0,
+        "correctedSimulationTime": 1500.0,
+      }))
+      .unwrap(),
+    );
+
+    let comparison = compare(&args(), &baseline, &candidate);
+
+    assert_eq!(comparison.missing.len(), 1);
+    assert_eq!(comparison.missing[0].operation, DynamoOperation::Read);
+    assert_eq!(
+      comparison.missing[0].phase,
+      Some(Phase::CorrectedSimulation)
+    );
+    assert_eq!(comparison.missing[0].missing_from, Side::Baseline);
+    assert_eq!(comparison.regressions, 0);
+  }
+
+  #[test]
+  fn compare_passes_identical_runs() {
+    let metrics = (0..50)
+      .map(|i| ("read", true, 1000.0 + i as f64))
+      .collect::<Vec<_>>();
+
+    let comparison = compare(&args(), &run_results(&metrics), &run_results(&metrics));
+
+    assert!(comparison.missing.is_empty());
+    assert_eq!(comparison.regressions, 0);
+  }
+}
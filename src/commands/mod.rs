@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use aws_sdk_dynamodb::{types::WriteRequest, Client};
+use clap::Subcommand;
+use log::{error, warn};
+
+use crate::attempts::recording_connector;
+
+use self::{
+  cleanup::CleanupArgs, compare::CompareArgs, report::ReportArgs, seed::SeedArgs,
+  table::TableCommand,
+};
+
+pub mod cleanup;
+pub mod compare;
+pub mod recorded;
+pub mod report;
+pub mod seed;
+pub mod table;
+
+/// The maximum number of items DynamoDB accepts in a single BatchWriteItem request
+pub const MAX_BATCH_WRITE_SIZE: usize = 25;
+const MAX_BATCH_WRITE_ATTEMPTS: u32 = 10;
+
+#[derive(Subcommand)]
+pub enum Command {
+  /// Populate the benchmarking table with randomly generated items
+  Seed(SeedArgs),
+  /// Create, describe, or delete a benchmarking table
+  #[command(subcommand)]
+  Table(TableCommand),
+  /// Delete the items left behind by cancelled or crashed benchmark runs
+  Cleanup(CleanupArgs),
+  /// Compare a candidate run against a baseline run, failing if the candidate significantly regressed
+  Compare(CompareArgs),
+  /// Generate a self-contained HTML report of a run, with charts over time, latency percentiles, errors, and the run's configuration
+  Report(ReportArgs),
+}
+
+impl Command {
+  pub async fn run(self) -> anyhow::Result<()> {
+    match self {
+      Command::Seed(args) => seed::run(args).await,
+      Command::Table(command) => table::run(command).await,
+      Command::Cleanup(args) => cleanup::run(args).await,
+      Command::Compare(args) => compare::run(args).await,
+      Command::Report(args) => report::run(args).await,
+    }
+  }
+}
+
+/// Creates a DynamoDB client from the environment, optionally pointed at a custom endpoint such as DynamoDB Local
+pub async fn dynamodb_client(endpoint_url: Option<&str>) -> Client {
+  let mut config_loader = aws_config::from_env().http_connector(recording_connector());
+  if let Some(endpoint_url) = endpoint_url {
+    config_loader = config_loader.endpoint_url(endpoint_url);
+  }
+
+  Client::new(&config_loader.load().await)
+}
+
+/// Sends a BatchWriteItem request, retrying any `UnprocessedItems` with an exponential backoff.
+///
+/// `on_written` is called with the number of items each attempt wrote, so callers can account for the items of a batch
+/// that were written before a later attempt failed
+pub async fn batch_write_with_retry(
+  dynamodb_client: &Client,
+  table_name: &str,
+  mut write_requests: Vec<WriteRequest>,
+  mut on_written: impl FnMut(u64),
+) -> anyhow::Result<()> {
+  for attempt in 0..MAX_BATCH_WRITE_ATTEMPTS {
+    let items_sent = write_requests.len();
+    let response = dynamodb_client
+      .batch_write_item()
+      .request_items(table_name, write_requests)
+      .send()
+      .await;
+
+    match response {
+      Ok(resp) => {
+        write_requests = resp
+          .unprocessed_items()
+          .and_then(|unprocessed_items| unprocessed_items.get(table_name))
+          .cloned()
+          .unwrap_or_default();
+        on_written((items_sent - write_requests.len()) as u64);
+
+        if write_requests.is_empty() {
+          return Ok(());
+        }
+        warn!(
+          "Attempt {attempt}: {} items were left unprocessed. Retrying...",
+          write_requests.len()
+        );
+      }
+      Err(e) => {
+        error!("Unable to batch write items to {table_name}! {e:?}");
+        return Err(anyhow!(e));
+      }
+    }
+
+    tokio::time::sleep(Duration::from_millis(50 * 2u64.pow(attempt))).await;
+  }
+
+  Err(anyhow!(
+    "Exhausted all {MAX_BATCH_WRITE_ATTEMPTS} attempts to write unprocessed items to {table_name}"
+  ))
+}
@@ -0,0 +1,343 @@
+use std::{
+  collections::BTreeMap,
+  fs::{self, File},
+  io::{BufRead, BufReader},
+  path::Path,
+};
+
+use anyhow::{anyhow, Context};
+use arrow_json::LineDelimitedWriter;
+use chrono::{DateTime, Utc};
+use clap::Args;
+use elasticsearch::{ClearScrollParts, GetParts, ScrollParts, SearchParts};
+use log::info;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+  histograms::Phase,
+  models::{errors::ErrorCategory, DynamoOperation},
+  sinks::{elasticsearch_client, metrics_files, run_metadata_path},
+};
+
+/// The number of metrics fetched from Elasticsearch per scroll request
+const SCROLL_SIZE: usize = 5000;
+
+/// Where to fetch runs from when they're given by run ID rather than as a metrics file
+#[derive(Args)]
+pub struct ElasticsearchArgs {
+  /// The URL of the Elasticsearch cluster to fetch runs from
+  #[arg(long, default_value_t = String::from("http://localhost:9200"))]
+  elasticsearch_url: String,
+  /// Local Elasticsearch cluster username
+  #[arg(short, long, default_value_t = String::from("elastic"))]
+  username: String,
+  /// Local Elasticsearch cluster password
+  #[arg(short, long, default_value_t = String::from("changeme"))]
+  password: String,
+  /// The Elasticsearch Index the runs' metrics were published to
+  #[arg(short, long, default_value_t = String::from("dynamodb"))]
+  index: String,
+}
+
+/// The parts of a published metric that are read back to analyze a finished run
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedMetrics {
+  pub operation: DynamoOperation,
+  pub timestamp: DateTime<Utc>,
+  pub successful: bool,
+  pub simulation_time: Option<f64>,
+  pub corrected_simulation_time: Option<f64>,
+  pub read_time: Option<f64>,
+  pub write_time: Option<f64>,
+  pub write_item_confirmation_time: Option<f64>,
+  pub update_time: Option<f64>,
+  pub update_item_confirmation_time: Option<f64>,
+  pub delete_time: Option<f64>,
+  pub delete_item_confirmation_time: Option<f64>,
+  pub error_category: Option<ErrorCategory>,
+  pub error_code: Option<String>,
+}
+
+impl RecordedMetrics {
+  pub fn latencies(&self) -> impl Iterator<Item = (Phase, f64)> {
+    [
+      (Phase::Simulation, self.simulation_time),
+      (Phase::CorrectedSimulation, self.corrected_simulation_time),
+      (Phase::Read, self.read_time),
+      (Phase::Write, self.write_time),
+      (Phase::WriteConfirmation, self.write_item_confirmation_time),
+      (Phase::Update, self.update_time),
+      (
+        Phase::UpdateConfirmation,
+        self.update_item_confirmation_time,
+      ),
+      (Phase::Delete, self.delete_time),
+      (
+        Phase::DeleteConfirmation,
+        self.delete_item_confirmation_time,
+      ),
+    ]
+    .into_iter()
+    .filter_map(|(phase, latency)| latency.map(|latency| (phase, latency)))
+  }
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct OperationTotals {
+  pub total: u64,
+  pub failed: u64,
+}
+
+/// The number of simulations of each operation in a run, and the span of time they were recorded over
+#[derive(Default)]
+pub struct RunTotals {
+  pub operations: BTreeMap<DynamoOperation, OperationTotals>,
+  pub first_timestamp: Option<DateTime<Utc>>,
+  pub last_timestamp: Option<DateTime<Utc>>,
+}
+
+impl RunTotals {
+  pub fn record(&mut self, metrics: &RecordedMetrics) {
+    let totals = self.operations.entry(metrics.operation).or_default();
+    totals.total += 1;
+    if !metrics.successful {
+      totals.failed += 1;
+    }
+
+    self.first_timestamp = Some(
+      self
+        .first_timestamp
+        .map_or(metrics.timestamp, |first| first.min(metrics.timestamp)),
+    );
+    self.last_timestamp = self.last_timestamp.max(Some(metrics.timestamp));
+  }
+
+  /// The totals of every operation combined
+  pub fn overall(&self) -> OperationTotals {
+    let mut overall = OperationTotals::default();
+    for totals in self.operations.values() {
+      overall.total += totals.total;
+      overall.failed += totals.failed;
+    }
+
+    overall
+  }
+
+  pub fn duration_seconds(&self) -> f64 {
+    match (self.first_timestamp, self.last_timestamp) {
+      (Some(first), Some(last)) => (last - first).num_milliseconds() as f64 / 1000.0,
+      _ => 0.0,
+    }
+  }
+
+  /// The rate of the given number of simulations over the run, per second
+  pub fn throughput(&self, count: u64) -> f64 {
+    let duration_seconds = self.duration_seconds();
+    if duration_seconds > 0.0 {
+      count as f64 / duration_seconds
+    } else {
+      0.0
+    }
+  }
+
+  pub fn operation_throughput(&self, operation: DynamoOperation) -> f64 {
+    self.throughput(
+      self
+        .operations
+        .get(&operation)
+        .map_or(0, |totals| totals.total),
+    )
+  }
+
+  pub fn error_rate(&self, operation: DynamoOperation) -> f64 {
+    match self.operations.get(&operation) {
+      Some(totals) if totals.total > 0 => totals.failed as f64 / totals.total as f64,
+      _ => 0.0,
+    }
+  }
+}
+
+/// Reads every metric of a run from its metrics file(s) if they exist, and otherwise from Elasticsearch by its run ID
+pub async fn load_run(
+  args: &ElasticsearchArgs,
+  run: &str,
+  mut record: impl FnMut(RecordedMetrics),
+) -> anyhow::Result<()> {
+  let mut count = 0;
+  let mut record = |metrics| {
+    count += 1;
+    record(metrics);
+  };
+
+  let files = metrics_files(Path::new(run));
+  if files.is_empty() {
+    info!("No metrics file found at {run}; fetching it from Elasticsearch as a run ID");
+    // Aggregated runs only published a summary of each time window, which has none of the per-simulation latencies needed here
+    let metadata = load_run_metadata(args, run).await?;
+    if metadata.is_some_and(|metadata| {
+      metadata["config"]["sinks"]["elasticsearchMode"].as_str() == Some("aggregated")
+    }) {
+      return Err(anyhow!(
+        "Run {run} was published with --elasticsearch-mode aggregated, so only its time windows were recorded (in windows-{}); \
+         it can only be read back if it was also published per simulation, e.g. to a metrics file",
+        args.index
+      ));
+    }
+    load_elasticsearch_run(args, run, &mut record).await?;
+  } else {
+    for file in files {
+      info!("Reading metrics from {}", file.display());
+      load_metrics_file(&file, &mut record)
+        .with_context(|| format!("Unable to read the metrics in {}", file.display()))?;
+    }
+  }
+
+  if count == 0 {
+    return Err(anyhow!("No metrics were found for {run}"));
+  }
+
+  Ok(())
+}
+
+/// Reads the metadata a run published when it started: from beside its metrics file(s), or from Elasticsearch by its run ID.
+/// Runs that didn't publish to a sink that records run metadata have none
+pub async fn load_run_metadata(
+  args: &ElasticsearchArgs,
+  run: &str,
+) -> anyhow::Result<Option<Value>> {
+  let path = Path::new(run);
+  if !metrics_files(path).is_empty() {
+    let metadata_path = run_metadata_path(path);
+    if !metadata_path.exists() {
+      return Ok(None);
+    }
+    return Ok(Some(serde_json::from_str(&fs::read_to_string(
+      metadata_path,
+    )?)?));
+  }
+
+  let es_client = elasticsearch_client(
+    &args.elasticsearch_url,
+    args.username.clone(),
+    args.password.clone(),
+  )?;
+  let runs_index = format!("runs-{}", args.index);
+  let response = es_client
+    .get(GetParts::IndexId(&runs_index, run))
+    .send()
+    .await?;
+  if !response.status_code().is_success() {
+    return Ok(None);
+  }
+
+  Ok(response.json::<Value>().await?.get("_source").cloned())
+}
+
+pub fn load_metrics_file(
+  path: &Path,
+  record: &mut impl FnMut(RecordedMetrics),
+) -> anyhow::Result<()> {
+  match path.extension().and_then(|extension| extension.to_str()) {
+    Some("csv") => {
+      for metrics in csv::Reader::from_path(path)?.deserialize() {
+        record(metrics?);
+      }
+    }
+    Some("parquet") => {
+      // The record batches are converted back to the JSON the metrics were originally decoded from
+      for batch in ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?.build()? {
+        let mut writer = LineDelimitedWriter::new(Vec::new());
+        writer.write(&batch?)?;
+        writer.finish()?;
+        for line in writer.into_inner().split(|&byte| byte == b'\n') {
+          if !line.is_empty() {
+            record(serde_json::from_slice(line)?);
+          }
+        }
+      }
+    }
+    _ => {
+      for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+          record(serde_json::from_str(&line)?);
+        }
+      }
+    }
+  }
+
+  Ok(())
+}
+
+async fn load_elasticsearch_run(
+  args: &ElasticsearchArgs,
+  run_id: &str,
+  record: &mut impl FnMut(RecordedMetrics),
+) -> anyhow::Result<()> {
+  let es_client = elasticsearch_client(
+    &args.elasticsearch_url,
+    args.username.clone(),
+    args.password.clone(),
+  )?;
+
+  let mut response = es_client
+    .search(SearchParts::Index(&[&args.index]))
+    .scroll("1m")
+    .size(SCROLL_SIZE as i64)
+    // Indices created before the run ID was explicitly mapped as a keyword have it dynamically mapped as text, with a keyword
+    // sub-field
+    .body(json!({
+      "query": {
+        "bool": {
+          "should": [
+            { "term": { "runId": run_id } },
+            { "term": { "runId.keyword": run_id } }
+          ],
+          "minimum_should_match": 1
+        }
+      }
+    }))
+    .send()
+    .await?
+    .error_for_status_code()?
+    .json::<Value>()
+    .await?;
+
+  loop {
+    let hits = response["hits"]["hits"]
+      .as_array()
+      .cloned()
+      .unwrap_or_default();
+    for hit in &hits {
+      record(serde_json::from_value(hit["_source"].clone())?);
+    }
+
+    let scroll_id = response["_scroll_id"].as_str().map(str::to_owned);
+    match scroll_id {
+      Some(scroll_id) if hits.len() == SCROLL_SIZE => {
+        response = es_client
+          .scroll(ScrollParts::None)
+          .body(json!({ "scroll": "1m", "scroll_id": scroll_id }))
+          .send()
+          .await?
+          .error_for_status_code()?
+          .json::<Value>()
+          .await?;
+      }
+      Some(scroll_id) => {
+        es_client
+          .clear_scroll(ClearScrollParts::None)
+          .body(json!({ "scroll_id": [scroll_id] }))
+          .send()
+          .await?;
+        break;
+      }
+      None => break,
+    }
+  }
+
+  Ok(())
+}
@@ -0,0 +1,496 @@
+use std::{collections::BTreeMap, fmt, fs, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use clap::Args;
+use log::info;
+use serde_json::Value;
+
+use crate::{
+  histograms::{LatencyHistograms, Phase},
+  models::{errors::ErrorCategory, DynamoOperation},
+};
+
+use super::recorded::{load_run, load_run_metadata, ElasticsearchArgs, RecordedMetrics, RunTotals};
+
+/// The number of points the charts over time are split into, unless a bucket width is given
+const DEFAULT_CHART_POINTS: f64 = 120.0;
+const CHART_WIDTH: f64 = 960.0;
+const CHART_HEIGHT: f64 = 280.0;
+const CHART_MARGIN_LEFT: f64 = 70.0;
+const CHART_MARGIN_BOTTOM: f64 = 30.0;
+const CHART_MARGIN_TOP: f64 = 10.0;
+const CHART_MARGIN_RIGHT: f64 = 20.0;
+const CHART_TICKS: usize = 5;
+const COLORS: [&str; 8] = [
+  "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+];
+
+#[derive(Args)]
+pub struct ReportArgs {
+  /// The run to report on: a metrics file written by the json-lines, csv or parquet sink, or the ID of a run published to Elasticsearch
+  run: String,
+  /// The HTML file to write the report to
+  #[arg(short, long, default_value = "report.html")]
+  output_file: PathBuf,
+  /// The width (in seconds) of each point in the charts over time. Defaults to splitting the run into about 120 points
+  #[arg(long)]
+  bucket_seconds: Option<u64>,
+  #[command(flatten)]
+  elasticsearch: ElasticsearchArgs,
+}
+
+/// A single simulation, as plotted on the charts over time
+struct Sample {
+  timestamp: DateTime<Utc>,
+  operation: DynamoOperation,
+  successful: bool,
+  simulation_time: Option<f64>,
+}
+
+/// What happened to every operation during one bucket of time
+#[derive(Default)]
+struct Bucket {
+  total: u64,
+  failed: u64,
+  /// Every simulation latency in the bucket, in milliseconds
+  latencies: Vec<f64>,
+}
+
+/// Everything a run recorded, read back from wherever its metrics were published, ready to be rendered as HTML
+#[derive(Default)]
+struct Report {
+  run: String,
+  metadata: Option<Value>,
+  totals: RunTotals,
+  latency_histograms: LatencyHistograms,
+  errors: BTreeMap<(DynamoOperation, Option<ErrorCategory>, Option<String>), u64>,
+  samples: Vec<Sample>,
+  bucket_seconds: Option<u64>,
+}
+
+impl Report {
+  fn record(&mut self, metrics: RecordedMetrics) {
+    self.totals.record(&metrics);
+    for (phase, latency) in metrics.latencies() {
+      self
+        .latency_histograms
+        .record(metrics.operation, phase, latency as u64);
+    }
+
+    if !metrics.successful || metrics.error_category.is_some() {
+      *self
+        .errors
+        .entry((
+          metrics.operation,
+          metrics.error_category,
+          metrics.error_code.clone(),
+        ))
+        .or_default() += 1;
+    }
+
+    self.samples.push(Sample {
+      timestamp: metrics.timestamp,
+      operation: metrics.operation,
+      successful: metrics.successful,
+      simulation_time: metrics.simulation_time,
+    });
+  }
+
+  /// Splits the simulations into buckets of time, returning the width of each bucket in seconds and the buckets of every
+  /// operation, keyed by the number of seconds into the run that they start at
+  fn buckets(&self) -> (u64, BTreeMap<DynamoOperation, BTreeMap<u64, Bucket>>) {
+    let bucket_seconds = self
+      .bucket_seconds
+      .unwrap_or_else(|| (self.totals.duration_seconds() / DEFAULT_CHART_POINTS).ceil() as u64)
+      .max(1);
+    let mut buckets: BTreeMap<DynamoOperation, BTreeMap<u64, Bucket>> = BTreeMap::new();
+    let Some(first_timestamp) = self.totals.first_timestamp else {
+      return (bucket_seconds, buckets);
+    };
+
+    for sample in &self.samples {
+      let offset = (sample.timestamp - first_timestamp).num_seconds() as u64;
+      let bucket = buckets
+        .entry(sample.operation)
+        .or_default()
+        .entry(offset / bucket_seconds * bucket_seconds)
+        .or_default();
+      bucket.total += 1;
+      if !sample.successful {
+        bucket.failed += 1;
+      }
+      if let Some(latency) = sample.simulation_time {
+        bucket.latencies.push(latency / 1000.0);
+      }
+    }
+    for bucket in buckets.values_mut().flat_map(BTreeMap::values_mut) {
+      bucket.latencies.sort_by(f64::total_cmp);
+    }
+
+    (bucket_seconds, buckets)
+  }
+}
+
+pub async fn run(args: ReportArgs) -> anyhow::Result<()> {
+  let mut report = Report {
+    run: args.run.clone(),
+    bucket_seconds: args.bucket_seconds,
+    ..Report::default()
+  };
+  load_run(&args.elasticsearch, &args.run, |metrics| {
+    report.record(metrics)
+  })
+  .await?;
+  report.metadata = load_run_metadata(&args.elasticsearch, &args.run).await?;
+  if report.metadata.is_none() {
+    info!(
+      "No run metadata was found for {}; the report won't include the run's configuration",
+      args.run
+    );
+  }
+
+  fs::write(&args.output_file, report.to_string())?;
+  info!("Wrote the report to {}", args.output_file.display());
+
+  Ok(())
+}
+
+/// A line on a chart, with x values in seconds into the run
+struct Series {
+  name: String,
+  points: Vec<(f64, f64)>,
+}
+
+/// The value at the given quantile of some sorted values
+fn quantile(sorted: &[f64], quantile: f64) -> Option<f64> {
+  if sorted.is_empty() {
+    return None;
+  }
+
+  let index = ((quantile * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len()) - 1;
+  Some(sorted[index])
+}
+
+/// Escapes text to be embedded in HTML
+fn escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+fn format_offset(seconds: f64) -> String {
+  let seconds = seconds.round() as u64;
+  format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Draws the series as an inline SVG line chart, so the report doesn't depend on any scripts or stylesheets
+fn write_line_chart(
+  f: &mut fmt::Formatter<'_>,
+  title: &str,
+  unit: &str,
+  duration_seconds: f64,
+  series: &[Series],
+) -> fmt::Result {
+  let plot_width = CHART_WIDTH - CHART_MARGIN_LEFT - CHART_MARGIN_RIGHT;
+  let plot_height = CHART_HEIGHT - CHART_MARGIN_TOP - CHART_MARGIN_BOTTOM;
+  let max_x = duration_seconds.max(1.0);
+  let max_y = series
+    .iter()
+    .flat_map(|series| series.points.iter().map(|&(_, y)| y))
+    .fold(0.0, f64::max)
+    .max(f64::EPSILON)
+    * 1.1;
+  let x = |value: f64| CHART_MARGIN_LEFT + value / max_x * plot_width;
+  let y = |value: f64| CHART_MARGIN_TOP + plot_height - value / max_y * plot_height;
+
+  writeln!(f, "<h3>{}</h3>", escape(title))?;
+  writeln!(
+    f,
+    r#"<svg class="chart" viewBox="0 0 {CHART_WIDTH} {CHART_HEIGHT}" xmlns="http://www.w3.org/2000/svg">"#
+  )?;
+  for tick in 0..=CHART_TICKS {
+    let fraction = tick as f64 / CHART_TICKS as f64;
+    let (tick_x, tick_y) = (x(fraction * max_x), y(fraction * max_y));
+    writeln!(
+      f,
+      r#"<line class="grid" x1="{CHART_MARGIN_LEFT}" x2="{}" y1="{tick_y:.1}" y2="{tick_y:.1}"/>"#,
+      CHART_MARGIN_LEFT + plot_width
+    )?;
+    writeln!(
+      f,
+      r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{:.1}</text>"#,
+      CHART_MARGIN_LEFT - 6.0,
+      tick_y + 4.0,
+      fraction * max_y
+    )?;
+    writeln!(
+      f,
+      r#"<text x="{tick_x:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+      CHART_HEIGHT - 8.0,
+      format_offset(fraction * max_x)
+    )?;
+  }
+  writeln!(
+    f,
+    r#"<text x="14" y="{:.1}" transform="rotate(-90 14 {:.1})" text-anchor="middle">{}</text>"#,
+    CHART_MARGIN_TOP + plot_height / 2.0,
+    CHART_MARGIN_TOP + plot_height / 2.0,
+    escape(unit)
+  )?;
+  for (i, series) in series.iter().enumerate() {
+    let points = series
+      .points
+      .iter()
+      .map(|&(px, py)| format!("{:.1},{:.1}", x(px), y(py)))
+      .collect::<Vec<String>>()
+      .join(" ");
+    writeln!(
+      f,
+      r#"<polyline fill="none" stroke="{}" stroke-width="1.5" points="{points}"><title>{}</title></polyline>"#,
+      COLORS[i % COLORS.len()],
+      escape(&series.name)
+    )?;
+  }
+  writeln!(f, "</svg>")?;
+
+  write!(f, r#"<div class="legend">"#)?;
+  for (i, series) in series.iter().enumerate() {
+    write!(
+      f,
+      r#"<span><i style="background:{}"></i>{}</span>"#,
+      COLORS[i % COLORS.len()],
+      escape(&series.name)
+    )?;
+  }
+  writeln!(f, "</div>")
+}
+
+impl fmt::Display for Report {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let metadata = self.metadata.as_ref();
+    let field = |name: &str| {
+      metadata
+        .and_then(|metadata| metadata.get(name))
+        .and_then(Value::as_str)
+        .map(escape)
+        .unwrap_or_else(|| String::from("unknown"))
+    };
+    let run_id = metadata
+      .and_then(|metadata| metadata["runId"].as_str())
+      .unwrap_or(&self.run);
+    let totals = self.totals.overall();
+
+    writeln!(f, "<!DOCTYPE html>")?;
+    writeln!(f, r#"<html lang="en"><head><meta charset="utf-8">"#)?;
+    writeln!(f, "<title>Benchmark report: {}</title>", escape(run_id))?;
+    writeln!(
+      f,
+      "<style>
+body {{ font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; margin: 2em auto; max-width: 1000px; color: #222; }}
+table {{ border-collapse: collapse; margin-bottom: 1em; }}
+th, td {{ border: 1px solid #ddd; padding: 4px 10px; }}
+td.number {{ text-align: right; font-variant-numeric: tabular-nums; }}
+th {{ background: #f4f4f4; text-align: left; }}
+pre {{ background: #f4f4f4; padding: 1em; overflow-x: auto; }}
+svg.chart {{ width: 100%; height: auto; font-size: 11px; }}
+svg.chart line.grid {{ stroke: #e5e5e5; }}
+.legend span {{ margin-right: 1.5em; font-size: 13px; }}
+.legend i {{ display: inline-block; width: 12px; height: 12px; margin-right: 4px; vertical-align: middle; }}
+</style></head><body>"
+    )?;
+
+    writeln!(f, "<h1>Benchmark report: {}</h1>", escape(run_id))?;
+    writeln!(f, "<h2>Overview</h2><table>")?;
+    let mut overview = vec![
+      ("Run ID", escape(run_id)),
+      ("Started at", field("startedAt")),
+      ("Hostname", field("hostname")),
+      ("Version", field("version")),
+    ];
+    if let Some(labels) = metadata
+      .and_then(|metadata| metadata["labels"].as_object())
+      .filter(|labels| !labels.is_empty())
+    {
+      overview.push((
+        "Labels",
+        escape(
+          &labels
+            .iter()
+            .map(|(key, value)| format!("{key}={}", value.as_str().unwrap_or_default()))
+            .collect::<Vec<String>>()
+            .join(", "),
+        ),
+      ));
+    }
+    overview.extend([
+      (
+        "Duration",
+        format!("{:.1}s", self.totals.duration_seconds()),
+      ),
+      (
+        "Simulations",
+        format!(
+          "{} ({} successful, {} failed)",
+          totals.total,
+          totals.total - totals.failed,
+          totals.failed
+        ),
+      ),
+      (
+        "Throughput",
+        format!("{:.2} simulations/s", self.totals.throughput(totals.total)),
+      ),
+    ]);
+    for (name, value) in overview {
+      writeln!(f, "<tr><th>{name}</th><td>{value}</td></tr>")?;
+    }
+    writeln!(f, "</table>")?;
+
+    writeln!(f, "<h2>Operations</h2><table>")?;
+    writeln!(
+      f,
+      "<tr><th>Operation</th><th>Total</th><th>Successful</th><th>Failed</th><th>Error rate</th><th>Throughput/s</th></tr>"
+    )?;
+    for (operation, operation_totals) in &self.totals.operations {
+      writeln!(
+        f,
+        r#"<tr><td>{operation:?}</td><td class="number">{}</td><td class="number">{}</td><td class="number">{}</td><td class="number">{:.2}%</td><td class="number">{:.2}</td></tr>"#,
+        operation_totals.total,
+        operation_totals.total - operation_totals.failed,
+        operation_totals.failed,
+        operation_totals.failed as f64 / operation_totals.total.max(1) as f64 * 100.0,
+        self.totals.throughput(operation_totals.total)
+      )?;
+    }
+    writeln!(f, "</table>")?;
+
+    let (bucket_seconds, buckets) = self.buckets();
+    let duration_seconds = self.totals.duration_seconds();
+    let per_second = |count: u64| count as f64 / bucket_seconds as f64;
+    writeln!(f, "<h2>Over time</h2>")?;
+    writeln!(
+      f,
+      "<p>Each point covers {bucket_seconds} second(s) of simulations, by when they started.</p>"
+    )?;
+    let latency_series = buckets
+      .iter()
+      .flat_map(|(operation, buckets)| {
+        [("p50", 0.5), ("p99", 0.99)].map(|(name, q)| Series {
+          name: format!("{operation:?} {name}"),
+          points: buckets
+            .iter()
+            .filter_map(|(&offset, bucket)| {
+              quantile(&bucket.latencies, q).map(|latency| (offset as f64, latency))
+            })
+            .collect(),
+        })
+      })
+      .collect::<Vec<Series>>();
+    write_line_chart(
+      f,
+      "Simulation latency",
+      "ms",
+      duration_seconds,
+      &latency_series,
+    )?;
+    let series = |value: fn(&Bucket) -> u64| {
+      buckets
+        .iter()
+        .map(|(operation, buckets)| Series {
+          name: format!("{operation:?}"),
+          points: buckets
+            .iter()
+            .map(|(&offset, bucket)| (offset as f64, per_second(value(bucket))))
+            .collect(),
+        })
+        .collect::<Vec<Series>>()
+    };
+    write_line_chart(
+      f,
+      "Throughput",
+      "simulations/s",
+      duration_seconds,
+      &series(|bucket| bucket.total),
+    )?;
+    write_line_chart(
+      f,
+      "Failures",
+      "failed simulations/s",
+      duration_seconds,
+      &series(|bucket| bucket.failed),
+    )?;
+
+    writeln!(f, "<h2>Latency percentiles (ms)</h2><table>")?;
+    writeln!(
+      f,
+      "<tr><th>Operation</th><th>Phase</th><th>Count</th><th>Mean</th><th>p50</th><th>p90</th><th>p99</th><th>p99.9</th><th>Max</th></tr>"
+    )?;
+    let millis = |micros: u64| micros as f64 / 1000.0;
+    let latencies = self.latency_histograms.percentiles();
+    for latency in &latencies {
+      writeln!(
+        f,
+        r#"<tr><td>{:?}</td><td>{:?}</td><td class="number">{}</td><td class="number">{:.3}</td><td class="number">{:.3}</td><td class="number">{:.3}</td><td class="number">{:.3}</td><td class="number">{:.3}</td><td class="number">{:.3}</td></tr>"#,
+        latency.operation,
+        latency.phase,
+        latency.count,
+        latency.mean / 1000.0,
+        millis(latency.p50),
+        millis(latency.p90),
+        millis(latency.p99),
+        millis(latency.p999),
+        millis(latency.max)
+      )?;
+    }
+    writeln!(f, "</table>")?;
+    if latencies
+      .iter()
+      .any(|latency| latency.phase == Phase::CorrectedSimulation)
+    {
+      writeln!(f, "<p>The CorrectedSimulation latencies are measured from when each simulation was scheduled to start, correcting for coordinated omission.</p>")?;
+    }
+
+    writeln!(f, "<h2>Errors</h2>")?;
+    if self.errors.is_empty() {
+      writeln!(f, "<p>None</p>")?;
+    } else {
+      writeln!(f, "<table>")?;
+      writeln!(
+        f,
+        "<tr><th>Operation</th><th>Category</th><th>Error code</th><th>Count</th></tr>"
+      )?;
+      for ((operation, category, code), count) in &self.errors {
+        writeln!(
+          f,
+          r#"<tr><td>{operation:?}</td><td>{}</td><td>{}</td><td class="number">{count}</td></tr>"#,
+          category.map_or_else(|| String::from("-"), |category| format!("{category:?}")),
+          code.as_deref().map_or_else(|| String::from("-"), escape)
+        )?;
+      }
+      writeln!(f, "</table>")?;
+    }
+
+    writeln!(f, "<h2>Configuration</h2>")?;
+    match metadata.and_then(|metadata| metadata.get("config")) {
+      Some(config) => writeln!(
+        f,
+        "<pre>{}</pre>",
+        escape(&serde_json::to_string_pretty(config).map_err(|_| fmt::Error)?)
+      )?,
+      None => writeln!(
+        f,
+        "<p>Not recorded; only runs published to a sink that records run metadata include their configuration.</p>"
+      )?,
+    }
+
+    writeln!(
+      f,
+      "<footer><p>Generated by dynamodb-benchmarker {} at {}</p></footer>",
+      env!("CARGO_PKG_VERSION"),
+      Utc::now().to_rfc3339()
+    )?;
+    writeln!(f, "</body></html>")
+  }
+}
@@ -0,0 +1,240 @@
+use std::{
+  env, fs,
+  path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+  },
+  time::Duration,
+};
+
+use anyhow::anyhow;
+use aws_sdk_dynamodb::types::{PutRequest, WriteRequest};
+use clap::Args;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::{select, task::JoinSet};
+use tokio_util::sync::CancellationToken;
+
+use crate::models::BenchmarkingItem;
+
+const BATCH_SIZE: u64 = super::MAX_BATCH_WRITE_SIZE as u64;
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Args)]
+pub struct SeedArgs {
+  /// The number of items to populate the table with
+  #[arg(short, long)]
+  items: u64,
+  /// The number of attributes to populate each item with; minimum value of 1
+  #[arg(short, long, default_value_t = 5)]
+  attributes: u32,
+  /// The DynamoDB table to populate
+  #[arg(short, long, default_value_t = format!("{}-high-velocity-table", env::var("USER").unwrap()))]
+  table_name: String,
+  /// A custom DynamoDB endpoint to use instead of the default AWS endpoint (e.g. http://localhost:8000 for DynamoDB Local)
+  #[arg(short, long)]
+  endpoint_url: Option<String>,
+  /// The number of BatchWriteItem requests to have in flight at once
+  #[arg(short, long, default_value_t = 16)]
+  parallelism: usize,
+  /// The file to record seeding progress in so an interrupted seed can be resumed [default: /tmp/<TABLE_NAME>-seed-checkpoint.json]
+  #[arg(long)]
+  checkpoint_file: Option<PathBuf>,
+  /// Ignore any existing checkpoint and seed the full number of items
+  #[arg(long)]
+  fresh: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SeedCheckpoint {
+  table_name: String,
+  attributes: u32,
+  total_items: u64,
+  items_written: u64,
+}
+
+impl SeedCheckpoint {
+  fn load(path: &Path) -> Option<SeedCheckpoint> {
+    let contents = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&contents) {
+      Ok(checkpoint) => Some(checkpoint),
+      Err(e) => {
+        warn!(
+          "Ignoring unreadable seed checkpoint {}: {e:?}",
+          path.display()
+        );
+        None
+      }
+    }
+  }
+
+  fn save(&self, path: &Path) -> anyhow::Result<()> {
+    fs::write(path, serde_json::to_string_pretty(self)?)?;
+    Ok(())
+  }
+
+  fn matches(&self, args: &SeedArgs) -> bool {
+    self.table_name == args.table_name
+      && self.attributes == args.attributes
+      && self.total_items == args.items
+  }
+}
+
+pub async fn run(args: SeedArgs) -> anyhow::Result<()> {
+  if args.attributes < 1 {
+    return Err(anyhow!("The number of attributes must be at least 1"));
+  }
+
+  let checkpoint_file = args
+    .checkpoint_file
+    .clone()
+    .unwrap_or_else(|| PathBuf::from(format!("/tmp/{}-seed-checkpoint.json", args.table_name)));
+  let already_written = match SeedCheckpoint::load(&checkpoint_file) {
+    Some(checkpoint) if !args.fresh && checkpoint.matches(&args) => {
+      info!(
+        "Resuming from checkpoint {}: {} of {} items already written",
+        checkpoint_file.display(),
+        checkpoint.items_written,
+        checkpoint.total_items
+      );
+      checkpoint.items_written.min(args.items)
+    }
+    Some(_) if !args.fresh => {
+      warn!(
+        "Checkpoint {} was created with different settings. Starting from scratch",
+        checkpoint_file.display()
+      );
+      0
+    }
+    _ => 0,
+  };
+
+  info!(
+    "Seeding {} items with {} attributes into {} using {} concurrent batch writers",
+    args.items - already_written,
+    args.attributes,
+    args.table_name,
+    args.parallelism
+  );
+
+  let dynamodb_client = super::dynamodb_client(args.endpoint_url.as_deref()).await;
+  let remaining_items = args.items - already_written;
+  let next_item = Arc::new(AtomicU64::new(0));
+  let items_written = Arc::new(AtomicU64::new(already_written));
+  let cancellation_token = CancellationToken::new();
+
+  let progress_bar = ProgressBar::new(args.items).with_style(ProgressStyle::with_template(
+    "{spinner} [{elapsed_precise}] [{wide_bar}] {pos}/{len} items ({per_sec}, ETA {eta})",
+  )?);
+  progress_bar.set_position(already_written);
+
+  let mut writers = JoinSet::new();
+  for _ in 0..args.parallelism.max(1) {
+    let dynamodb_client = dynamodb_client.clone();
+    let table_name = args.table_name.clone();
+    let next_item = next_item.clone();
+    let items_written = items_written.clone();
+    let progress_bar = progress_bar.clone();
+    let token = cancellation_token.clone();
+    let attributes = args.attributes;
+
+    writers.spawn(async move {
+      while !token.is_cancelled() {
+        let start = next_item.fetch_add(BATCH_SIZE, Ordering::SeqCst);
+        if start >= remaining_items {
+          break;
+        }
+        let batch_size = BATCH_SIZE.min(remaining_items - start);
+        let write_requests = (0..batch_size)
+          .map(|_| {
+            WriteRequest::builder()
+              .put_request(
+                PutRequest::builder()
+                  .set_item(Some(BenchmarkingItem::new(attributes).extract_map()))
+                  .build(),
+              )
+              .build()
+          })
+          .collect();
+
+        // Count each attempt's writes as they land so a batch that fails partway isn't re-seeded on resume
+        super::batch_write_with_retry(&dynamodb_client, &table_name, write_requests, |written| {
+          items_written.fetch_add(written, Ordering::SeqCst);
+          progress_bar.inc(written);
+        })
+        .await?;
+      }
+
+      Ok::<(), anyhow::Error>(())
+    });
+  }
+
+  let checkpoint = |items_written: &AtomicU64| SeedCheckpoint {
+    table_name: args.table_name.clone(),
+    attributes: args.attributes,
+    total_items: args.items,
+    items_written: items_written.load(Ordering::SeqCst),
+  };
+  let mut checkpoint_interval = tokio::time::interval(CHECKPOINT_INTERVAL);
+  let mut result = Ok(());
+  let ctrl_c = tokio::signal::ctrl_c();
+  tokio::pin!(ctrl_c);
+
+  loop {
+    select! {
+      joined = writers.join_next() => match joined {
+        Some(Ok(Ok(_))) => (),
+        Some(Ok(Err(e))) => {
+          error!("A batch writer failed; stopping the seed: {e:?}");
+          cancellation_token.cancel();
+          result = Err(e);
+        }
+        Some(Err(e)) => {
+          error!("A batch writer panicked; stopping the seed: {e:?}");
+          cancellation_token.cancel();
+          result = Err(anyhow!(e));
+        }
+        None => break,
+      },
+      _ = checkpoint_interval.tick() => {
+        // A failed checkpoint only costs progress if the seed is interrupted, so it shouldn't stop the seed
+        if let Err(e) = checkpoint(&items_written).save(&checkpoint_file) {
+          warn!("Unable to save the seed checkpoint {}: {e:?}", checkpoint_file.display());
+        }
+      }
+      _ = &mut ctrl_c, if !cancellation_token.is_cancelled() => {
+        warn!("Interrupted. Waiting for in-flight batches to finish...");
+        cancellation_token.cancel();
+        result = Err(anyhow!("Seeding was interrupted"));
+      }
+    }
+  }
+
+  progress_bar.finish();
+
+  if result.is_ok() {
+    if checkpoint_file.exists() {
+      fs::remove_file(&checkpoint_file)?;
+    }
+    info!(
+      "Successfully seeded {} items into {}",
+      args.items, args.table_name
+    );
+  } else {
+    match checkpoint(&items_written).save(&checkpoint_file) {
+      Ok(_) => warn!(
+        "Seeding progress saved to {}. Re-run the same command to resume",
+        checkpoint_file.display()
+      ),
+      Err(e) => error!(
+        "Unable to save seeding progress to {}: {e:?}",
+        checkpoint_file.display()
+      ),
+    }
+  }
+
+  result
+}
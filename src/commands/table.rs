@@ -0,0 +1,391 @@
+use std::{
+  env,
+  time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use aws_sdk_dynamodb::{
+  types::{
+    AttributeDefinition, BillingMode, GlobalSecondaryIndex, KeySchemaElement, KeyType, Projection,
+    ProjectionType, ProvisionedThroughput, ScalarAttributeType, StreamSpecification,
+    StreamViewType, TableDescription, TableStatus, TimeToLiveSpecification,
+  },
+  Client,
+};
+use clap::{Args, Subcommand, ValueEnum};
+use log::{info, warn};
+
+use crate::models::BenchmarkingItem;
+
+const TABLE_STATUS_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Subcommand)]
+pub enum TableCommand {
+  /// Create a benchmarking table and wait for it to become ACTIVE
+  Create(CreateTableArgs),
+  /// Describe the settings and current state of a benchmarking table
+  Describe(TableArgs),
+  /// Delete a benchmarking table and wait for the deletion to complete
+  Delete(TableArgs),
+}
+
+#[derive(Args)]
+pub struct TableArgs {
+  /// The DynamoDB table to operate on
+  #[arg(short, long, default_value_t = format!("{}-high-velocity-table", env::var("USER").unwrap()))]
+  table_name: String,
+  /// A custom DynamoDB endpoint to use instead of the default AWS endpoint (e.g. http://localhost:8000 for DynamoDB Local)
+  #[arg(short, long)]
+  endpoint_url: Option<String>,
+  /// The maximum length of time (in seconds) to wait for the table to reach the desired state
+  #[arg(long, default_value_t = 300)]
+  wait_timeout: u64,
+}
+
+#[derive(Args)]
+pub struct CreateTableArgs {
+  #[command(flatten)]
+  table: TableArgs,
+  /// The billing mode of the table
+  #[arg(short, long, value_enum, default_value_t = TableBillingMode::OnDemand)]
+  billing_mode: TableBillingMode,
+  /// The provisioned read capacity units of the table and its indexes; only used in provisioned mode
+  #[arg(short, long, default_value_t = 5)]
+  read_capacity: i64,
+  /// The provisioned write capacity units of the table and its indexes; only used in provisioned mode
+  #[arg(short, long, default_value_t = 5)]
+  write_capacity: i64,
+  /// A generated attribute (e.g. 0, 1, 2, ...) to create a global secondary index on; may be specified multiple times
+  #[arg(short, long)]
+  gsi: Vec<u32>,
+  /// The attribute to enable Time to Live on
+  #[arg(long)]
+  ttl_attribute: Option<String>,
+  /// Enable DynamoDB Streams on the table with the given view type
+  #[arg(short, long, value_enum)]
+  stream_view_type: Option<TableStreamViewType>,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum TableBillingMode {
+  OnDemand,
+  Provisioned,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum TableStreamViewType {
+  KeysOnly,
+  NewImage,
+  OldImage,
+  NewAndOldImages,
+}
+
+impl From<TableStreamViewType> for StreamViewType {
+  fn from(value: TableStreamViewType) -> Self {
+    match value {
+      TableStreamViewType::KeysOnly => StreamViewType::KeysOnly,
+      TableStreamViewType::NewImage => StreamViewType::NewImage,
+      TableStreamViewType::OldImage => StreamViewType::OldImage,
+      TableStreamViewType::NewAndOldImages => StreamViewType::NewAndOldImages,
+    }
+  }
+}
+
+pub async fn run(command: TableCommand) -> anyhow::Result<()> {
+  match command {
+    TableCommand::Create(args) => create_table(args).await,
+    TableCommand::Describe(args) => describe_table(args).await,
+    TableCommand::Delete(args) => delete_table(args).await,
+  }
+}
+
+async fn create_table(args: CreateTableArgs) -> anyhow::Result<()> {
+  let dynamodb_client = super::dynamodb_client(args.table.endpoint_url.as_deref()).await;
+  let table_name = &args.table.table_name;
+  let provisioned_throughput = match args.billing_mode {
+    TableBillingMode::OnDemand => None,
+    TableBillingMode::Provisioned => Some(
+      ProvisionedThroughput::builder()
+        .read_capacity_units(args.read_capacity)
+        .write_capacity_units(args.write_capacity)
+        .build(),
+    ),
+  };
+
+  let mut create_table_request = dynamodb_client
+    .create_table()
+    .table_name(table_name)
+    .attribute_definitions(
+      AttributeDefinition::builder()
+        .attribute_name("id")
+        .attribute_type(ScalarAttributeType::S)
+        .build(),
+    )
+    .key_schema(
+      KeySchemaElement::builder()
+        .attribute_name("id")
+        .key_type(KeyType::Hash)
+        .build(),
+    )
+    .set_provisioned_throughput(provisioned_throughput.clone())
+    .billing_mode(match args.billing_mode {
+      TableBillingMode::OnDemand => BillingMode::PayPerRequest,
+      TableBillingMode::Provisioned => BillingMode::Provisioned,
+    });
+
+  for attribute in &args.gsi {
+    let attribute_name = attribute.to_string();
+    create_table_request = create_table_request
+      .attribute_definitions(
+        AttributeDefinition::builder()
+          .attribute_name(&attribute_name)
+          .attribute_type(BenchmarkingItem::attribute_type(*attribute))
+          .build(),
+      )
+      .global_secondary_indexes(
+        GlobalSecondaryIndex::builder()
+          .index_name(format!("gsi-{attribute_name}"))
+          .key_schema(
+            KeySchemaElement::builder()
+              .attribute_name(&attribute_name)
+              .key_type(KeyType::Hash)
+              .build(),
+          )
+          .projection(
+            Projection::builder()
+              .projection_type(ProjectionType::All)
+              .build(),
+          )
+          .set_provisioned_throughput(provisioned_throughput.clone())
+          .build(),
+      );
+  }
+
+  if let Some(stream_view_type) = args.stream_view_type {
+    create_table_request = create_table_request.stream_specification(
+      StreamSpecification::builder()
+        .stream_enabled(true)
+        .stream_view_type(stream_view_type.into())
+        .build(),
+    );
+  }
+
+  info!("Creating table {table_name}...");
+  create_table_request.send().await?;
+
+  wait_for_table_status(
+    &dynamodb_client,
+    table_name,
+    TableStatus::Active,
+    args.table.wait_timeout,
+  )
+  .await?;
+
+  if let Some(ttl_attribute) = args.ttl_attribute {
+    info!("Enabling Time to Live on attribute {ttl_attribute}...");
+    dynamodb_client
+      .update_time_to_live()
+      .table_name(table_name)
+      .time_to_live_specification(
+        TimeToLiveSpecification::builder()
+          .enabled(true)
+          .attribute_name(ttl_attribute)
+          .build(),
+      )
+      .send()
+      .await?;
+  }
+
+  info!("Table {table_name} is ACTIVE");
+
+  Ok(())
+}
+
+async fn describe_table(args: TableArgs) -> anyhow::Result<()> {
+  let dynamodb_client = super::dynamodb_client(args.endpoint_url.as_deref()).await;
+  let table = fetch_table_description(&dynamodb_client, &args.table_name)
+    .await?
+    .ok_or_else(|| anyhow!("Table {} does not exist", args.table_name))?;
+  let time_to_live = dynamodb_client
+    .describe_time_to_live()
+    .table_name(&args.table_name)
+    .send()
+    .await
+    .map_err(|e| warn!("Unable to describe the Time to Live settings: {e:?}"))
+    .ok()
+    .and_then(|resp| resp.time_to_live_description().cloned());
+
+  println!("Table:        {}", table.table_name().unwrap_or_default());
+  println!("ARN:          {}", table.table_arn().unwrap_or_default());
+  println!("Status:       {}", status_str(table.table_status()));
+  println!("Item count:   {}", table.item_count().unwrap_or_default());
+  println!(
+    "Size (bytes): {}",
+    table.table_size_bytes().unwrap_or_default()
+  );
+  println!("Key schema:   {}", key_schema_str(table.key_schema()));
+
+  match table
+    .billing_mode_summary()
+    .and_then(|summary| summary.billing_mode())
+  {
+    Some(BillingMode::PayPerRequest) => println!("Billing mode: on-demand"),
+    _ => {
+      let throughput = table.provisioned_throughput();
+      println!(
+        "Billing mode: provisioned ({} RCU, {} WCU)",
+        throughput
+          .and_then(|t| t.read_capacity_units())
+          .unwrap_or_default(),
+        throughput
+          .and_then(|t| t.write_capacity_units())
+          .unwrap_or_default()
+      );
+    }
+  }
+
+  for index in table.global_secondary_indexes().unwrap_or_default() {
+    println!(
+      "GSI:          {} [{}] {}",
+      index.index_name().unwrap_or_default(),
+      key_schema_str(index.key_schema()),
+      index
+        .index_status()
+        .map(|status| status.as_str())
+        .unwrap_or("UNKNOWN")
+    );
+  }
+
+  match table
+    .stream_specification()
+    .filter(|stream| stream.stream_enabled().unwrap_or_default())
+  {
+    Some(stream) => println!(
+      "Stream:       {} ({})",
+      stream
+        .stream_view_type()
+        .map(|view_type| view_type.as_str())
+        .unwrap_or_default(),
+      table.latest_stream_arn().unwrap_or_default()
+    ),
+    None => println!("Stream:       disabled"),
+  }
+
+  match time_to_live {
+    Some(ttl) => println!(
+      "TTL:          {} {}",
+      ttl
+        .time_to_live_status()
+        .map(|status| status.as_str())
+        .unwrap_or("UNKNOWN"),
+      ttl.attribute_name().unwrap_or_default()
+    ),
+    None => println!("TTL:          UNKNOWN"),
+  }
+
+  Ok(())
+}
+
+async fn delete_table(args: TableArgs) -> anyhow::Result<()> {
+  let dynamodb_client = super::dynamodb_client(args.endpoint_url.as_deref()).await;
+
+  info!("Deleting table {}...", args.table_name);
+  dynamodb_client
+    .delete_table()
+    .table_name(&args.table_name)
+    .send()
+    .await?;
+
+  let start = Instant::now();
+  while fetch_table_description(&dynamodb_client, &args.table_name)
+    .await?
+    .is_some()
+  {
+    if start.elapsed() > Duration::from_secs(args.wait_timeout) {
+      return Err(anyhow!(
+        "Timed out waiting for table {} to be deleted",
+        args.table_name
+      ));
+    }
+    tokio::time::sleep(TABLE_STATUS_POLL_INTERVAL).await;
+  }
+
+  info!("Table {} was deleted", args.table_name);
+
+  Ok(())
+}
+
+async fn fetch_table_description(
+  dynamodb_client: &Client,
+  table_name: &str,
+) -> anyhow::Result<Option<TableDescription>> {
+  match dynamodb_client
+    .describe_table()
+    .table_name(table_name)
+    .send()
+    .await
+  {
+    Ok(resp) => Ok(resp.table().cloned()),
+    Err(e) => {
+      let service_error = e.into_service_error();
+      if service_error.is_resource_not_found_exception() {
+        Ok(None)
+      } else {
+        Err(anyhow!(service_error))
+      }
+    }
+  }
+}
+
+async fn wait_for_table_status(
+  dynamodb_client: &Client,
+  table_name: &str,
+  desired_status: TableStatus,
+  wait_timeout: u64,
+) -> anyhow::Result<()> {
+  let start = Instant::now();
+  loop {
+    let table = fetch_table_description(dynamodb_client, table_name).await?;
+    let status = table.as_ref().and_then(|table| table.table_status());
+    if status == Some(&desired_status) {
+      return Ok(());
+    }
+
+    if start.elapsed() > Duration::from_secs(wait_timeout) {
+      return Err(anyhow!(
+        "Timed out waiting for table {table_name} to become {}; it is currently {}",
+        desired_status.as_str(),
+        status_str(status)
+      ));
+    }
+
+    info!(
+      "Table {table_name} is {}. Waiting for it to become {}...",
+      status_str(status),
+      desired_status.as_str()
+    );
+    tokio::time::sleep(TABLE_STATUS_POLL_INTERVAL).await;
+  }
+}
+
+fn status_str(status: Option<&TableStatus>) -> &str {
+  status.map(|status| status.as_str()).unwrap_or("UNKNOWN")
+}
+
+fn key_schema_str(key_schema: Option<&[KeySchemaElement]>) -> String {
+  key_schema
+    .unwrap_or_default()
+    .iter()
+    .map(|element| {
+      format!(
+        "{} ({})",
+        element.attribute_name().unwrap_or_default(),
+        element
+          .key_type()
+          .map(|key_type| key_type.as_str())
+          .unwrap_or_default()
+      )
+    })
+    .collect::<Vec<String>>()
+    .join(", ")
+}
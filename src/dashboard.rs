@@ -0,0 +1,475 @@
+use std::{
+  cmp::Reverse,
+  collections::{BTreeMap, VecDeque},
+  io,
+  sync::{
+    atomic::Ordering,
+    mpsc::{self, RecvTimeoutError, Sender, TryRecvError},
+    Arc, Mutex,
+  },
+  thread::{self, JoinHandle},
+  time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use log::error;
+use ratatui::{
+  crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+  layout::{Constraint, Layout},
+  style::{Color, Modifier, Style},
+  text::Line,
+  widgets::{Block, Gauge, Paragraph, Row, Sparkline, Table},
+  DefaultTerminal, Frame,
+};
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+  histograms::{LatencyPercentiles, Phase},
+  models::{errors::ErrorCategory, DynamoOperation, Scenario},
+  sinks::RunGauges,
+  summary::{OperationCounts, SimulationStats},
+};
+
+const DASHBOARD_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+/// How many refreshes of throughput history the dashboard keeps for its sparkline
+const THROUGHPUT_HISTORY: usize = 300;
+/// How many of the most common errors the dashboard lists
+const TOP_ERRORS: usize = 5;
+
+#[derive(ValueEnum, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum DisplayMode {
+  Logs,
+  Dashboard,
+  Progress,
+}
+
+/// What the simulations have done so far, so that it can be displayed while the run is in progress. Each simulation task records
+/// into its own shard, so the tasks never contend with each other, and the shards are merged into a snapshot on every refresh
+pub struct LiveStats {
+  shards: Vec<Arc<Mutex<SimulationStats>>>,
+}
+
+impl LiveStats {
+  pub fn new(tasks: usize) -> LiveStats {
+    LiveStats {
+      shards: (0..tasks).map(|_| Arc::default()).collect(),
+    }
+  }
+
+  /// The stats the given simulation task records into
+  pub fn shard(&self, task: usize) -> Arc<Mutex<SimulationStats>> {
+    self.shards[task].clone()
+  }
+
+  /// A copy of every task's stats merged together, so they can be summarized without holding up the tasks
+  fn snapshot(&self) -> SimulationStats {
+    let mut snapshot = SimulationStats::default();
+    for shard in &self.shards {
+      snapshot.merge(&shard.lock().unwrap());
+    }
+    snapshot
+  }
+}
+
+/// The details of the run that the dashboard shows alongside its progress
+pub struct RunDescription {
+  pub run_id: String,
+  pub table_name: String,
+  pub scenario: Scenario,
+  pub concurrent_simulations: u32,
+  pub duration: Duration,
+}
+
+/// Shows the progress of a run on a background thread, either as a full-screen dashboard or as a line printed periodically
+pub struct ProgressDisplay {
+  handle: Option<JoinHandle<()>>,
+  finished: Option<Sender<()>>,
+}
+
+impl ProgressDisplay {
+  /// Starts showing the run's progress in the given mode; in the `logs` mode, which records no live stats, nothing is shown.
+  /// Quitting the dashboard (with q, Esc or Ctrl-C) cancels the run early
+  pub fn spawn(
+    mode: DisplayMode,
+    run: RunDescription,
+    live_stats: Option<Arc<LiveStats>>,
+    gauges: Arc<RunGauges>,
+    cancellation_token: CancellationToken,
+    progress_interval: Duration,
+  ) -> ProgressDisplay {
+    let (finished_tx, finished_rx) = mpsc::channel();
+    let tracker = live_stats.map(|live_stats| ProgressTracker::new(run, live_stats, gauges));
+    let handle = match (mode, tracker) {
+      (DisplayMode::Dashboard, Some(tracker)) => Some(thread::spawn(move || {
+        if let Err(e) = show_dashboard(tracker, finished_rx, cancellation_token) {
+          error!("Unable to show the dashboard: {e:?}");
+        }
+      })),
+      (DisplayMode::Progress, Some(tracker)) => Some(thread::spawn(move || {
+        print_progress(tracker, finished_rx, progress_interval)
+      })),
+      _ => None,
+    };
+
+    ProgressDisplay {
+      handle,
+      finished: Some(finished_tx),
+    }
+  }
+
+  /// Stops showing the run's progress, restoring the terminal if the dashboard was shown
+  pub fn stop(mut self) {
+    drop(self.finished.take());
+    if let Some(handle) = self.handle.take() {
+      if handle.join().is_err() {
+        error!("The progress display thread panicked");
+      }
+    }
+  }
+}
+
+struct OperationProgress {
+  operation: DynamoOperation,
+  counts: OperationCounts,
+  /// The throughput since the previous refresh
+  throughput_per_second: f64,
+  mean_throughput_per_second: f64,
+  /// The percentiles of the simulation latencies so far
+  latency: Option<LatencyPercentiles>,
+}
+
+/// A point-in-time view of the run's progress
+struct RunProgress {
+  elapsed: Duration,
+  remaining: Duration,
+  cancelled: bool,
+  active_simulations: usize,
+  operations: Vec<OperationProgress>,
+  totals: OperationCounts,
+  throughput_per_second: f64,
+  mean_throughput_per_second: f64,
+  retried_requests: u64,
+  error_categories: BTreeMap<ErrorCategory, u64>,
+  /// The most common errors, most common first
+  errors: Vec<(String, u64)>,
+}
+
+/// Turns the live stats into the run's progress, keeping track of what's changed since the previous refresh
+struct ProgressTracker {
+  run: RunDescription,
+  live_stats: Arc<LiveStats>,
+  gauges: Arc<RunGauges>,
+  started: Instant,
+  previous_refresh: Instant,
+  previous_totals: BTreeMap<DynamoOperation, u64>,
+  throughput_history: VecDeque<u64>,
+}
+
+impl ProgressTracker {
+  fn new(run: RunDescription, live_stats: Arc<LiveStats>, gauges: Arc<RunGauges>) -> Self {
+    let now = Instant::now();
+    ProgressTracker {
+      run,
+      live_stats,
+      gauges,
+      started: now,
+      previous_refresh: now,
+      previous_totals: BTreeMap::new(),
+      throughput_history: VecDeque::with_capacity(THROUGHPUT_HISTORY),
+    }
+  }
+
+  fn refresh(&mut self, cancelled: bool) -> RunProgress {
+    let now = Instant::now();
+    let elapsed = now - self.started;
+    let since_previous_refresh = (now - self.previous_refresh).as_secs_f64();
+    self.previous_refresh = now;
+    let throughput = |count: u64, seconds: f64| {
+      if seconds > 0.0 {
+        count as f64 / seconds
+      } else {
+        0.0
+      }
+    };
+
+    let stats = self.live_stats.snapshot();
+    let mut latencies = stats
+      .latency_histograms
+      .percentiles()
+      .into_iter()
+      .filter(|latency| latency.phase == Phase::Simulation)
+      .map(|latency| (latency.operation, latency))
+      .collect::<BTreeMap<DynamoOperation, LatencyPercentiles>>();
+    let mut totals = OperationCounts::default();
+    let operations = stats
+      .operations
+      .iter()
+      .map(|(&operation, counts)| {
+        totals.merge(counts);
+        let previous_total = self
+          .previous_totals
+          .insert(operation, counts.total)
+          .unwrap_or_default();
+        OperationProgress {
+          operation,
+          counts: *counts,
+          throughput_per_second: throughput(counts.total - previous_total, since_previous_refresh),
+          mean_throughput_per_second: throughput(counts.total, elapsed.as_secs_f64()),
+          latency: latencies.remove(&operation),
+        }
+      })
+      .collect::<Vec<OperationProgress>>();
+    let mut errors = stats
+      .errors
+      .iter()
+      .map(|(error, &count)| (error.clone(), count))
+      .collect::<Vec<(String, u64)>>();
+    errors.sort_by_key(|(_, count)| Reverse(*count));
+    errors.truncate(TOP_ERRORS);
+    let retried_requests = stats.retries.retried_requests;
+    let error_categories = stats.error_categories.clone();
+
+    let throughput_per_second = operations
+      .iter()
+      .map(|operation| operation.throughput_per_second)
+      .sum::<f64>();
+    if self.throughput_history.len() == THROUGHPUT_HISTORY {
+      self.throughput_history.pop_front();
+    }
+    self
+      .throughput_history
+      .push_back(throughput_per_second.round() as u64);
+
+    RunProgress {
+      elapsed,
+      remaining: self.run.duration.saturating_sub(elapsed),
+      cancelled,
+      active_simulations: self.gauges.active_simulations.load(Ordering::Relaxed),
+      operations,
+      totals,
+      throughput_per_second,
+      mean_throughput_per_second: throughput(totals.total, elapsed.as_secs_f64()),
+      retried_requests,
+      error_categories,
+      errors,
+    }
+  }
+}
+
+fn show_dashboard(
+  mut tracker: ProgressTracker,
+  finished: mpsc::Receiver<()>,
+  cancellation_token: CancellationToken,
+) -> io::Result<()> {
+  let mut terminal = ratatui::try_init()?;
+  let result = run_dashboard(&mut terminal, &mut tracker, finished, cancellation_token);
+  ratatui::try_restore()?;
+  result
+}
+
+fn run_dashboard(
+  terminal: &mut DefaultTerminal,
+  tracker: &mut ProgressTracker,
+  finished: mpsc::Receiver<()>,
+  cancellation_token: CancellationToken,
+) -> io::Result<()> {
+  loop {
+    let progress = tracker.refresh(cancellation_token.is_cancelled());
+    terminal.draw(|frame| render_dashboard(frame, tracker, &progress))?;
+
+    let next_refresh = Instant::now() + DASHBOARD_REFRESH_INTERVAL;
+    while let Some(timeout) = next_refresh.checked_duration_since(Instant::now()) {
+      if !matches!(finished.try_recv(), Err(TryRecvError::Empty)) {
+        return Ok(());
+      }
+
+      // The terminal is in raw mode, so Ctrl-C arrives as a key press rather than a signal
+      if event::poll(timeout.min(Duration::from_millis(100)))? {
+        if let Event::Key(key) = event::read()? {
+          let quit = key.code == KeyCode::Char('q')
+            || key.code == KeyCode::Esc
+            || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL));
+          if key.kind == KeyEventKind::Press && quit {
+            cancellation_token.cancel();
+          }
+        }
+      }
+    }
+  }
+}
+
+fn render_dashboard(frame: &mut Frame, tracker: &ProgressTracker, progress: &RunProgress) {
+  let run = &tracker.run;
+  let [header, operations, throughput, errors, footer] = Layout::vertical([
+    Constraint::Length(3),
+    Constraint::Length(progress.operations.len() as u16 + 4),
+    Constraint::Length(8),
+    Constraint::Min(4),
+    Constraint::Length(1),
+  ])
+  .areas(frame.area());
+
+  let ratio = if run.duration.is_zero() {
+    1.0
+  } else {
+    (progress.elapsed.as_secs_f64() / run.duration.as_secs_f64()).min(1.0)
+  };
+  frame.render_widget(
+    Gauge::default()
+      .block(Block::bordered().title(format!(
+        " Run {} against {} ({:?}) ",
+        run.run_id, run.table_name, run.scenario
+      )))
+      .gauge_style(Style::default().fg(Color::Cyan))
+      .ratio(ratio)
+      .label(format!(
+        "{} / {} ({} remaining) | {}/{} active simulations",
+        format_duration(progress.elapsed),
+        format_duration(run.duration),
+        format_duration(progress.remaining),
+        progress.active_simulations,
+        run.concurrent_simulations
+      )),
+    header,
+  );
+
+  let millis = |micros: u64| format!("{:.3}", micros as f64 / 1000.0);
+  let mut rows = progress
+    .operations
+    .iter()
+    .map(|operation| {
+      let mut cells = vec![
+        format!("{:?}", operation.operation),
+        operation.counts.total.to_string(),
+        operation.counts.failed.to_string(),
+        format!("{:.2}", operation.throughput_per_second),
+        format!("{:.2}", operation.mean_throughput_per_second),
+      ];
+      match &operation.latency {
+        Some(latency) => cells.extend([
+          millis(latency.p50),
+          millis(latency.p90),
+          millis(latency.p99),
+          millis(latency.max),
+        ]),
+        None => cells.extend([
+          "-".to_owned(),
+          "-".to_owned(),
+          "-".to_owned(),
+          "-".to_owned(),
+        ]),
+      }
+      Row::new(cells)
+    })
+    .collect::<Vec<Row>>();
+  rows.push(
+    Row::new(vec![
+      "Total".to_owned(),
+      progress.totals.total.to_string(),
+      progress.totals.failed.to_string(),
+      format!("{:.2}", progress.throughput_per_second),
+      format!("{:.2}", progress.mean_throughput_per_second),
+    ])
+    .style(Style::default().add_modifier(Modifier::BOLD)),
+  );
+  frame.render_widget(
+    Table::new(rows, [Constraint::Length(10); 9])
+      .header(
+        Row::new([
+          "Operation",
+          "Total",
+          "Failed",
+          "Now/s",
+          "Mean/s",
+          "p50 (ms)",
+          "p90 (ms)",
+          "p99 (ms)",
+          "Max (ms)",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD)),
+      )
+      .block(Block::bordered().title(" Simulations ")),
+    operations,
+  );
+
+  frame.render_widget(
+    Sparkline::default()
+      .block(Block::bordered().title(format!(
+        " Throughput: {:.2} simulations/s ",
+        progress.throughput_per_second
+      )))
+      .style(Style::default().fg(Color::Green))
+      .data(tracker.throughput_history.iter().copied()),
+    throughput,
+  );
+
+  let mut error_lines = vec![Line::from(format!(
+    "Requests retried by the SDK: {}",
+    progress.retried_requests
+  ))];
+  error_lines.extend(
+    progress
+      .error_categories
+      .iter()
+      .map(|(category, count)| Line::from(format!("{category:?}: {count}"))),
+  );
+  error_lines.extend(
+    progress
+      .errors
+      .iter()
+      .map(|(error, count)| Line::from(format!("{count:>8} {error}")).style(Color::Red)),
+  );
+  frame.render_widget(
+    Paragraph::new(error_lines).block(Block::bordered().title(" Errors ")),
+    errors,
+  );
+
+  frame.render_widget(
+    Line::from(if progress.cancelled {
+      " Stopping the run; waiting for the in-flight simulations to be cancelled..."
+    } else {
+      " Press q to stop the run early"
+    })
+    .style(Style::default().add_modifier(Modifier::DIM)),
+    footer,
+  );
+}
+
+fn print_progress(mut tracker: ProgressTracker, finished: mpsc::Receiver<()>, interval: Duration) {
+  while let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(interval) {
+    let progress = tracker.refresh(false);
+    let mut line = format!(
+      "[{} elapsed, {} remaining] {} active | {} simulations, {} failed | {:.2}/s ({:.2}/s mean)",
+      format_duration(progress.elapsed),
+      format_duration(progress.remaining),
+      progress.active_simulations,
+      progress.totals.total,
+      progress.totals.failed,
+      progress.throughput_per_second,
+      progress.mean_throughput_per_second
+    );
+    for operation in &progress.operations {
+      if let Some(latency) = &operation.latency {
+        line.push_str(&format!(
+          " | {:?} p50 {:.1}ms p99 {:.1}ms",
+          operation.operation,
+          latency.p50 as f64 / 1000.0,
+          latency.p99 as f64 / 1000.0
+        ));
+      }
+    }
+    println!("{line}");
+  }
+}
+
+fn format_duration(duration: Duration) -> String {
+  let seconds = duration.as_secs();
+  format!(
+    "{:02}:{:02}:{:02}",
+    seconds / 3600,
+    seconds % 3600 / 60,
+    seconds % 60
+  )
+}
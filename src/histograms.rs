@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+
+use hdrhistogram::Histogram;
+use serde::Serialize;
+use serde_json::Number;
+
+use crate::models::{DynamoDbSimulationMetrics, DynamoOperation};
+
+const SIGNIFICANT_FIGURES: u8 = 3;
+/// One hour in microseconds; anything slower is clamped to this
+const HIGHEST_TRACKABLE_LATENCY: u64 = 3_600_000_000;
+
+/// The individual timed phases of a simulation
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Phase {
+  Simulation,
+  CorrectedSimulation,
+  Read,
+  Write,
+  WriteConfirmation,
+  Update,
+  UpdateConfirmation,
+  Delete,
+  DeleteConfirmation,
+}
+
+impl Phase {
+  /// The latency of every phase the simulation went through
+  pub fn latencies(metrics: &DynamoDbSimulationMetrics) -> impl Iterator<Item = (Phase, &Number)> {
+    [
+      (Phase::Simulation, &metrics.simulation_time),
+      (
+        Phase::CorrectedSimulation,
+        &metrics.corrected_simulation_time,
+      ),
+      (Phase::Read, &metrics.read_time),
+      (Phase::Write, &metrics.write_time),
+      (
+        Phase::WriteConfirmation,
+        &metrics.write_item_confirmation_time,
+      ),
+      (Phase::Update, &metrics.update_time),
+      (
+        Phase::UpdateConfirmation,
+        &metrics.update_item_confirmation_time,
+      ),
+      (Phase::Delete, &metrics.delete_time),
+      (
+        Phase::DeleteConfirmation,
+        &metrics.delete_item_confirmation_time,
+      ),
+    ]
+    .into_iter()
+    .filter_map(|(phase, latency)| latency.as_ref().map(|latency| (phase, latency)))
+  }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyPercentiles {
+  pub operation: DynamoOperation,
+  pub phase: Phase,
+  pub count: u64,
+  pub min: u64,
+  pub mean: f64,
+  pub p50: u64,
+  pub p90: u64,
+  pub p99: u64,
+  pub p999: u64,
+  pub max: u64,
+}
+
+/// HDR histograms of the latencies of every phase of every simulated operation.
+/// Each simulation task records into its own instance, which are merged together when the tasks finish
+#[derive(Default)]
+pub struct LatencyHistograms {
+  histograms: BTreeMap<(DynamoOperation, Phase), Histogram<u64>>,
+}
+
+impl LatencyHistograms {
+  pub fn record(&mut self, operation: DynamoOperation, phase: Phase, latency: u64) {
+    self
+      .histograms
+      .entry((operation, phase))
+      .or_insert_with(new_histogram)
+      .saturating_record(latency);
+  }
+
+  pub fn record_metrics(&mut self, metrics: &DynamoDbSimulationMetrics) {
+    for (phase, latency) in Phase::latencies(metrics) {
+      if let Some(latency) = latency.as_u64() {
+        self.record(metrics.operation, phase, latency);
+      }
+    }
+  }
+
+  pub fn merge(&mut self, other: &LatencyHistograms) {
+    for (key, histogram) in &other.histograms {
+      self
+        .histograms
+        .entry(*key)
+        .or_insert_with(new_histogram)
+        .add(histogram)
+        .unwrap();
+    }
+  }
+
+  pub fn percentiles(&self) -> Vec<LatencyPercentiles> {
+    self
+      .histograms
+      .iter()
+      .map(|(&(operation, phase), histogram)| LatencyPercentiles {
+        operation,
+        phase,
+        count: histogram.len(),
+        min: histogram.min(),
+        mean: histogram.mean(),
+        p50: histogram.value_at_quantile(0.5),
+        p90: histogram.value_at_quantile(0.9),
+        p99: histogram.value_at_quantile(0.99),
+        p999: histogram.value_at_quantile(0.999),
+        max: histogram.max(),
+      })
+      .collect()
+  }
+}
+
+fn new_histogram() -> Histogram<u64> {
+  Histogram::new_with_bounds(1, HIGHEST_TRACKABLE_LATENCY, SIGNIFICANT_FIGURES).unwrap()
+}
@@ -1,47 +1,68 @@
-use std::{env, time::Duration};
-
-use anyhow::anyhow;
+use std::{
+  collections::HashSet,
+  env,
+  io::{self, IsTerminal},
+  path::{Path, PathBuf},
+  sync::{atomic::Ordering, Arc, Mutex},
+  time::Duration,
+};
 
-use aws_sdk_dynamodb::Client;
 use chrono::Utc;
-use clap::Parser;
-use elasticsearch::{
-  auth::Credentials,
-  http::{
-    transport::{SingleNodeConnectionPool, TransportBuilder},
-    Url,
-  },
-  indices::IndicesPutMappingParts,
-  Elasticsearch,
-};
+use clap::{error::ErrorKind, CommandFactory, Parser};
 use log::{error, info, warn, LevelFilter};
 use log4rs::{
-  append::console::ConsoleAppender,
+  append::{console::ConsoleAppender, file::FileAppender},
   config::{Appender, Root},
   encode::pattern::PatternEncoder,
 };
-use models::{DynamoDbSimulationMetrics, DynamoOperation};
+use models::{run::RunMetadata, DynamoDbSimulationMetrics, DynamoOperation};
+use opentelemetry::context::FutureExt;
 use rand::{
   rngs::{OsRng, StdRng},
   Rng, SeedableRng,
 };
-use serde_json::json;
+use serde::Serialize;
+use serde_json::Number;
 use tokio::{
   select,
-  sync::mpsc::{self, Receiver, Sender},
+  sync::mpsc::{self, Sender},
   task::JoinHandle,
+  time::Instant,
 };
 use tokio_util::sync::CancellationToken;
 
-use crate::{models::Scenario, simulators::Simulator};
+use crate::{
+  commands::{
+    cleanup::{delete_items, DEFAULT_DELETE_PARALLELISM},
+    Command,
+  },
+  dashboard::{DisplayMode, LiveStats, ProgressDisplay, RunDescription},
+  models::Scenario,
+  partition_keys::{fetch_partition_keys, PartitionKeyArgs},
+  simulators::{ConfirmationArgs, ConsumedCapacityDetail, SimulationContext, Simulator},
+  sinks::{create_sinks, start_metrics_publisher, RunGauges, SinkArgs},
+  summary::{RunSummary, SimulationStats},
+};
 
+mod attempts;
+mod commands;
+mod dashboard;
+mod histograms;
 mod models;
+mod partition_keys;
 mod simulators;
+mod sinks;
+mod summary;
+mod telemetry;
 mod timer_utils;
 
-#[derive(Parser)]
+#[derive(Parser, Serialize)]
 #[command(author, version, about, long_about = None)]
+#[serde(rename_all = "camelCase")]
 struct Cli {
+  #[command(subcommand)]
+  #[serde(skip)]
+  command: Option<Command>,
   /// The number of concurrent simulations to run
   #[arg(short, long, default_value_t = 1000)]
   concurrent_simulations: u32,
@@ -51,67 +72,177 @@ struct Cli {
   /// The length of time (in seconds) to run the benchmark for
   #[arg(short, long, default_value_t = 1800)]
   duration: u64,
-  /// The buffer size of the Elasticsearch thread's MPSC channel
-  #[arg(short, long, default_value_t = 500)]
-  buffer: usize,
-  /// Local Elasticsearch cluster username
-  #[arg(short, long, default_value_t = String::from("elastic"))]
-  username: String,
-  /// Local Elasticsearch cluster password
-  #[arg(short, long, default_value_t = String::from("changeme"))]
-  password: String,
-  /// The Elasticsearch Index to insert data into
-  #[arg(short, long, default_value_t = String::from("dynamodb"))]
-  index: String,
+  #[command(flatten)]
+  sinks: SinkArgs,
   /// The DynamoDB table to perform operations against
   #[arg(short, long, default_value_t = format!("{}-high-velocity-table", env::var("USER").unwrap()))]
   table_name: String,
+  /// A custom DynamoDB endpoint to use instead of the default AWS endpoint (e.g. http://localhost:8000 for DynamoDB Local)
+  #[arg(short, long)]
+  endpoint_url: Option<String>,
   /// Whether to run a read-only scenario for benchmarking
   #[arg(short, long)]
   read_only: bool,
+  /// Pace the simulations to this many per second in total, spread evenly across the concurrent simulations.
+  /// Each simulation's total latency is then also measured from when it was scheduled to start, correcting for coordinated omission;
+  /// the latencies of the individual phases aren't corrected
+  #[arg(long, value_parser = parse_rate)]
+  rate: Option<f64>,
+  /// How much detail DynamoDB returns about the read and write capacity units consumed by each call
+  #[arg(long, value_enum, default_value_t = ConsumedCapacityDetail::Total)]
+  return_consumed_capacity: ConsumedCapacityDetail,
+  #[command(flatten)]
+  partition_keys: PartitionKeyArgs,
+  #[command(flatten)]
+  confirmation: ConfirmationArgs,
+  /// Write the end-of-run summary to this file as JSON
+  #[arg(long)]
+  summary_file: Option<PathBuf>,
+  /// Skip deleting the items left behind by cancelled simulations when the run ends
+  #[arg(long)]
+  skip_cleanup: bool,
+  /// A key=value label to attach to the run and every metric it publishes (e.g. --label env=staging); may be specified multiple times.
+  /// Keys may only contain letters, digits and underscores, and may not shadow the labels the sinks already attach (e.g. operation or run_id)
+  #[arg(long = "label", value_parser = parse_label)]
+  #[serde(skip)]
+  labels: Vec<(String, String)>,
+  /// How to show the run's progress: by logging every step of every simulation, with a live full-screen dashboard, or by printing
+  /// a line of progress every --progress-interval seconds. The dashboard falls back to progress lines when stdout isn't a terminal.
+  /// The dashboard and progress lines can't be combined with the stdout sink
+  #[arg(long, value_enum, default_value_t = DisplayMode::Logs)]
+  display: DisplayMode,
+  /// How often (in seconds) to print a line of progress when --display is progress
+  #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+  progress_interval: u64,
+  /// Write the logs to this file instead of stdout. When --display is dashboard or progress, the logs are written to
+  /// <RUN_ID>.log by default
+  #[arg(long)]
+  log_file: Option<PathBuf>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
   let cli = Cli::parse();
-  log4rs::init_config(init_logging_config())?;
+  if cli.display != DisplayMode::Logs && cli.sinks.writes_to_stdout() {
+    // The metrics would be printed over the dashboard and in between the progress lines
+    Cli::command()
+      .error(
+        ErrorKind::ArgumentConflict,
+        "the stdout sink can't be used with --display dashboard or --display progress",
+      )
+      .exit();
+  }
+  let display = if cli.display == DisplayMode::Dashboard && !io::stdout().is_terminal() {
+    DisplayMode::Progress
+  } else {
+    cli.display
+  };
+  if display != cli.display {
+    eprintln!(
+      "stdout isn't a terminal, so printing progress lines instead of showing the dashboard"
+    );
+  }
+  let run = Arc::new(RunMetadata::new(&cli.labels, serde_json::to_value(&cli)?));
+  // Logs written to the terminal would drown out the progress lines and corrupt the dashboard, so they're written to a file
+  // named after the run instead, so that warnings and errors (e.g. from a sink that can't publish) aren't lost
+  let default_log_file =
+    (cli.command.is_none() && cli.log_file.is_none() && display != DisplayMode::Logs)
+      .then(|| PathBuf::from(format!("{}.log", run.run_id)));
+  let log_file = cli.log_file.as_deref().or(default_log_file.as_deref());
+  log4rs::init_config(init_logging_config(log_file)?)?;
+
+  if let Some(command) = cli.command {
+    return command.run().await;
+  }
+
   let cancellation_token = CancellationToken::new();
 
-  let (es_tx, es_rx) = mpsc::channel::<DynamoDbSimulationMetrics>(cli.buffer);
-  std::thread::spawn(move || {
-    start_elasticsearch_publisher(es_rx, cli.username, cli.password, cli.index)
+  info!(
+    "Starting benchmark run {} on {} (version {})",
+    run.run_id, run.hostname, run.version
+  );
+
+  let gauges = Arc::new(RunGauges::default());
+  // Nothing reads the live stats when logging, so they're only recorded for the dashboard and progress lines
+  let live_stats = (display != DisplayMode::Logs)
+    .then(|| Arc::new(LiveStats::new(cli.concurrent_simulations as usize)));
+  let sinks = create_sinks(&cli.sinks, &cli.table_name, &run, gauges.clone()).await?;
+  let (metrics_tx, metrics_rx) = mpsc::channel::<DynamoDbSimulationMetrics>(cli.sinks.buffer);
+  let publisher_run = run.clone();
+  let publisher_gauges = gauges.clone();
+  let metrics_publisher = std::thread::spawn(move || {
+    start_metrics_publisher(metrics_rx, sinks, publisher_run, publisher_gauges)
+  });
+
+  let dynamodb_client = commands::dynamodb_client(cli.endpoint_url.as_deref()).await;
+  let partition_keys =
+    fetch_partition_keys(&dynamodb_client, &cli.table_name, &cli.partition_keys).await?;
+  let context = Arc::new(SimulationContext {
+    dynamodb_client,
+    table_name: cli.table_name,
+    consumed_capacity_detail: cli.return_consumed_capacity,
+    attributes: cli.attributes,
+    partition_keys,
+    confirmation: cli.confirmation,
+    run_id: run.run_id.clone(),
+    in_flight_items: Mutex::new(HashSet::new()),
+    simulation_stats: Mutex::new(SimulationStats::default()),
   });
 
+  let simulation_interval = cli
+    .rate
+    .map(|rate| Duration::from_secs_f64(cli.concurrent_simulations as f64 / rate));
+  let scenario = if cli.read_only {
+    Scenario::ReadOnly
+  } else {
+    Scenario::Crud
+  };
+  let started_at = Utc::now();
   let handles: Vec<JoinHandle<_>> = (0..cli.concurrent_simulations)
-    .map(|_| {
-      let tx = es_tx.clone();
+    .map(|task| {
+      let tx = metrics_tx.clone();
       let token = cancellation_token.clone();
-      let table_name = cli.table_name.clone();
+      let context = context.clone();
+      let run = run.clone();
+      let gauges = gauges.clone();
+      let simulation_stats = live_stats
+        .as_ref()
+        .map_or_else(Arc::default, |live_stats| live_stats.shard(task as usize));
 
       tokio::spawn(async move {
-        let config = aws_config::load_from_env().await;
-        let dynamodb_client = Client::new(&config);
-        match scan_all_partition_keys(&dynamodb_client, table_name.clone()).await {
-          Ok(partition_keys_vec) => {
-            let simulator = Simulator::new(
-              &dynamodb_client,
-              table_name.clone(),
-              cli.attributes,
-              &partition_keys_vec,
-            );
-            select! {
-              _ = token.cancelled() => {
-                warn!("Task cancelled. Shutting down...");
-              }
-              _ = simulation_loop(simulator, cli.read_only, tx) => ()
-            }
+        gauges.active_simulations.fetch_add(1, Ordering::Relaxed);
+        let simulator = Simulator::new(&context);
+        select! {
+          _ = token.cancelled() => {
+            info!("Task cancelled. Shutting down...");
           }
-          Err(e) => error!("Unable to fetch partition keys: {e:?}"),
+          _ = simulation_loop(simulator, &run, cli.read_only, simulation_interval, tx, &simulation_stats) => ()
         }
+        context
+          .simulation_stats
+          .lock()
+          .unwrap()
+          .merge(&simulation_stats.lock().unwrap());
+        gauges.active_simulations.fetch_sub(1, Ordering::Relaxed);
       })
     })
     .collect();
 
+  let progress_display = ProgressDisplay::spawn(
+    display,
+    RunDescription {
+      run_id: run.run_id.clone(),
+      table_name: context.table_name.clone(),
+      scenario,
+      concurrent_simulations: cli.concurrent_simulations,
+      duration: Duration::from_secs(cli.duration),
+    },
+    live_stats,
+    gauges.clone(),
+    cancellation_token.clone(),
+    Duration::from_secs(cli.progress_interval),
+  );
+
   tokio::spawn(async move {
     info!(
       "Starting timer task. Executing for {} seconds",
@@ -129,96 +260,138 @@ async fn main() -> anyhow::Result<()> {
       Err(e) => warn!("Task did not shut down gracefully {e:?}"),
     }
   }
+  tokio::task::spawn_blocking(move || progress_display.stop()).await?;
+  if let Some(log_file) = &default_log_file {
+    eprintln!("The run's logs were written to {}", log_file.display());
+  }
+
+  let summary = RunSummary::new(
+    &run,
+    context.table_name.clone(),
+    scenario,
+    started_at,
+    Utc::now(),
+    &context.simulation_stats.lock().unwrap(),
+  );
+  println!("{summary}");
+  if let Some(summary_file) = &cli.summary_file {
+    match summary.write_json(summary_file) {
+      Ok(_) => info!("Wrote the run summary to {}", summary_file.display()),
+      Err(e) => error!(
+        "Unable to write the run summary to {}: {e:?}",
+        summary_file.display()
+      ),
+    }
+  }
+
+  if !cli.skip_cleanup {
+    clean_up_in_flight_items(&context).await;
+  }
+
+  info!("Waiting for the remaining metrics to be published...");
+  drop(metrics_tx);
+  if tokio::task::spawn_blocking(move || metrics_publisher.join())
+    .await?
+    .is_err()
+  {
+    error!("The metrics publisher thread panicked");
+  }
 
   Ok(())
 }
 
-#[tokio::main]
-async fn start_elasticsearch_publisher(
-  mut elasticsearch_rx: Receiver<DynamoDbSimulationMetrics>,
-  username: String,
-  password: String,
-  index: String,
-) -> anyhow::Result<()> {
-  let url = Url::parse("http://localhost:9200")?;
-  let connection_pool = SingleNodeConnectionPool::new(url);
-  let credentials = Credentials::Basic(username, password);
-  let transport = TransportBuilder::new(connection_pool)
-    .auth(credentials)
-    .build()?;
-  let es_client = Elasticsearch::new(transport);
-
-  info!("Setting the explicit mappings for the {index} index");
-  es_client
-    .indices()
-    .put_mapping(IndicesPutMappingParts::Index(&[&index]))
-    .body(json!({
-      "properties": {
-        "timestamp": {
-          "type": "date"
-        }
-      }
-    }))
-    .send()
-    .await?;
-
-  while let Some(metric) = elasticsearch_rx.recv().await {
-    info!("Publishing metrics to Elasticsearch...");
-
-    let es_response = es_client
-      .index(elasticsearch::IndexParts::Index(&index))
-      .body(metric)
-      .send()
-      .await;
-
-    match es_response {
-      Ok(resp) => {
-        if resp.status_code().is_success() {
-          info!("Successfully published metrics to Elasticsearch");
-        } else {
-          error!("Was unable to publish metrics to Elasticsearch! Received non 2XX response");
-        }
-      }
-      Err(e) => {
-        error!("Unable to publish metrics to Elasticsearch! {e:?}");
-      }
-    }
+/// Best-effort deletion of the items that cancelled simulations wrote but never got the chance to delete
+async fn clean_up_in_flight_items(context: &SimulationContext) {
+  let partition_keys = context
+    .in_flight_items
+    .lock()
+    .unwrap()
+    .drain()
+    .collect::<Vec<String>>();
+  if partition_keys.is_empty() {
+    return;
   }
 
-  Ok(())
+  info!(
+    "Cleaning up {} items left behind by cancelled simulations...",
+    partition_keys.len()
+  );
+  match delete_items(
+    &context.dynamodb_client,
+    &context.table_name,
+    partition_keys,
+    DEFAULT_DELETE_PARALLELISM,
+  )
+  .await
+  {
+    Ok(items_deleted) => info!("Cleaned up {items_deleted} items"),
+    Err(e) => error!(
+      "Unable to clean up the items left behind by cancelled simulations. Run `cleanup --run-id {}` to remove them: {e:?}",
+      context.run_id
+    ),
+  }
 }
 
 async fn simulation_loop(
   mut simulator: Simulator<'_>,
+  run: &RunMetadata,
   read_only: bool,
+  interval: Option<Duration>,
   tx: Sender<DynamoDbSimulationMetrics>,
+  simulation_stats: &Mutex<SimulationStats>,
 ) {
   let mut rng = StdRng::from_seed(OsRng.gen());
+  // When pacing, each simulation is scheduled a fixed interval after the previous one was meant to start, regardless of how long
+  // it actually took, so a stall is charged to every simulation that should have started in the meantime. The first start is
+  // staggered so the tasks don't all fire at once
+  let mut schedule =
+    interval.map(|interval| (interval, Instant::now() + interval.mul_f64(rng.gen())));
   loop {
+    if let Some((_, intended_start)) = schedule {
+      tokio::time::sleep_until(intended_start).await;
+    }
+
     let mut metrics = DynamoDbSimulationMetrics {
+      run_id: run.run_id.clone(),
+      labels: run.labels.clone(),
       timestamp: Utc::now(),
       ..DynamoDbSimulationMetrics::default()
     };
 
-    let simulation_time = time!(match {
-      if read_only {
-        info!("Running a read-only simulation...");
-        metrics.scenario = Scenario::ReadOnly;
-        run_read_only_simulation(&mut simulator, &mut metrics, &mut rng).await
-      } else {
-        info!("Running a CRUD simulation...");
-        metrics.scenario = Scenario::Crud;
-        run_crud_simulation(&mut simulator, &mut metrics, &mut rng).await
-      }
-    } {
-      Ok(_) => {
-        info!("Simulation completed successfully!");
-        metrics.successful = true;
+    let simulation_span = telemetry::start_simulation_span();
+    let (simulation_time, result) = time!(
+      result,
+      async {
+        if read_only {
+          info!("Running a read-only simulation...");
+          metrics.scenario = Scenario::ReadOnly;
+          run_read_only_simulation(&mut simulator, &mut metrics, &mut rng).await
+        } else {
+          info!("Running a CRUD simulation...");
+          metrics.scenario = Scenario::Crud;
+          run_crud_simulation(&mut simulator, &mut metrics, &mut rng).await
+        }
       }
+      .with_context(simulation_span.clone())
+      .await
+    );
+    if let Some((interval, intended_start)) = schedule.as_mut() {
+      metrics.corrected_simulation_time = Some(Number::from(intended_start.elapsed().as_micros()));
+      *intended_start += *interval;
+    }
+
+    match &result {
+      Ok(_) => info!("Simulation completed successfully!"),
       Err(e) => error!("Simulation did not complete. Encountered the following error: {e:?}"),
-    });
+    }
+    metrics.record_outcome(result.as_ref().err());
     metrics.simulation_time = Some(simulation_time);
     info!("Metrics: {metrics:?}");
+    simulation_stats
+      .lock()
+      .unwrap()
+      .record(&metrics, result.as_ref().err());
+    telemetry::end_simulation_span(&simulation_span, &metrics, result.as_ref().err());
 
     match tx.send(metrics).await {
       Ok(_) => info!("Metrics sent down channel successfully"),
@@ -227,6 +400,63 @@ async fn simulation_loop(
   }
 }
 
+/// Parses the target rate, which must be a positive number for the interval between simulations to be finite
+fn parse_rate(rate: &str) -> Result<f64, String> {
+  let rate: f64 = rate
+    .parse()
+    .map_err(|_| format!("The rate must be a number, but got: {rate}"))?;
+  if !rate.is_finite() || rate <= 0.0 {
+    return Err(format!(
+      "The rate must be a positive number of simulations per second, but got: {rate}"
+    ));
+  }
+
+  Ok(rate)
+}
+
+/// The label and tag names the sinks already attach to metrics, which the run's labels may not shadow
+const RESERVED_LABEL_KEYS: &[&str] = &[
+  "run_id",
+  "table",
+  "operation",
+  "scenario",
+  "outcome",
+  "phase",
+  "category",
+  "type",
+  "request",
+  "reason",
+  "hostname",
+  "version",
+  "le",
+];
+
+/// Parses a `key=value` label. Keys are restricted to the characters every sink accepts in label and tag names
+fn parse_label(label: &str) -> Result<(String, String), String> {
+  let (key, value) = label
+    .split_once('=')
+    .ok_or_else(|| format!("Labels must be in the form key=value, but got: {label}"))?;
+  if key.is_empty()
+    || key.starts_with(|c: char| c.is_ascii_digit())
+    || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+  {
+    return Err(format!(
+      "Label keys may only contain letters, digits and underscores, and may not start with a digit, but got: {key}"
+    ));
+  }
+  if key.starts_with("__") || RESERVED_LABEL_KEYS.contains(&key) {
+    return Err(format!(
+      "The label key {key} is reserved for the labels and tags the sinks attach to every metric, and keys may not start with __"
+    ));
+  }
+  // InfluxDB rejects a whole batch of lines over a single empty tag value
+  if value.is_empty() {
+    return Err(format!("The label {key} has no value"));
+  }
+
+  Ok((key.to_owned(), value.to_owned()))
+}
+
 async fn run_read_only_simulation(
   simulator: &mut Simulator<'_>,
   metrics: &mut DynamoDbSimulationMetrics,
@@ -263,55 +493,64 @@ async fn run_crud_simulation(
   Ok(())
 }
 
-async fn scan_all_partition_keys(
-  dynamodb_client: &Client,
-  table_name: String,
-) -> anyhow::Result<Vec<String>> {
-  info!("Fetching a large list of partition keys to randomly read...");
-  let response = dynamodb_client
-    .scan()
-    .table_name(table_name)
-    .limit(10000)
-    .projection_expression("id")
-    .send()
-    .await;
-
-  match response {
-    Ok(resp) => {
-      info!("Fetched partition keys!");
-      let partition_keys = resp
-        .items()
-        .unwrap()
-        .iter()
-        .map(|attribute| {
-          attribute
-            .values()
-            .last()
-            .unwrap()
-            .as_s()
-            .unwrap()
-            .to_string()
-        })
-        .collect::<Vec<String>>();
-      info!("Found a total of {} keys", partition_keys.len());
-      Ok(partition_keys)
-    }
-    Err(e) => {
-      error!("Unable to fetch partition keys! {e:?}");
-      Err(anyhow!(e))
+fn init_logging_config(log_file: Option<&Path>) -> anyhow::Result<log4rs::Config> {
+  let encoder = || {
+    Box::new(PatternEncoder::new(
+      "{d(%Y-%m-%d %H:%M:%S%.3f)(utc)} <{i}> [{l}] {f}:{L} - {m}{n}",
+    ))
+  };
+  let appender: Box<dyn log4rs::append::Append> = match log_file {
+    Some(log_file) => Box::new(FileAppender::builder().encoder(encoder()).build(log_file)?),
+    None => Box::new(ConsoleAppender::builder().encoder(encoder()).build()),
+  };
+
+  Ok(
+    log4rs::Config::builder()
+      .appender(Appender::builder().build("log", appender))
+      .build(Root::builder().appender("log").build(LevelFilter::Info))?,
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_label_splits_the_key_and_value() {
+    assert_eq!(
+      parse_label("env=staging=blue"),
+      Ok((String::from("env"), String::from("staging=blue")))
+    );
+  }
+
+  #[test]
+  fn parse_label_rejects_invalid_and_reserved_keys_and_empty_values() {
+    for label in [
+      "env",
+      "env=",
+      "=staging",
+      "1env=staging",
+      "env-name=staging",
+      "operation=read",
+      "run_id=abc",
+      "le=1",
+      "__name__=x",
+      "__env=staging",
+    ] {
+      assert!(parse_label(label).is_err(), "{label} should be rejected");
     }
   }
-}
 
-fn init_logging_config() -> log4rs::Config {
-  let stdout = ConsoleAppender::builder()
-    .encoder(Box::new(PatternEncoder::new(
-      "{d(%Y-%m-%d %H:%M:%S%.3f)(utc)} <{i}> [{l}] {f}:{L} - {m}{n}",
-    )))
-    .build();
+  #[test]
+  fn parse_rate_accepts_positive_rates() {
+    assert_eq!(parse_rate("0.5"), Ok(0.5));
+    assert_eq!(parse_rate("1000"), Ok(1000.0));
+  }
 
-  log4rs::Config::builder()
-    .appender(Appender::builder().build("stdout", Box::new(stdout)))
-    .build(Root::builder().appender("stdout").build(LevelFilter::Info))
-    .unwrap()
+  #[test]
+  fn parse_rate_rejects_rates_that_would_not_pace_the_simulations() {
+    for rate in ["0", "-1", "-0.0", "inf", "NaN", "fast"] {
+      assert!(parse_rate(rate).is_err(), "{rate} should be rejected");
+    }
+  }
 }
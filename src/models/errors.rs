@@ -0,0 +1,124 @@
+use std::{error::Error, fmt};
+
+use aws_sdk_dynamodb::{
+  error::{ProvideErrorMetadata, SdkError},
+  operation::RequestId,
+};
+use serde::{Deserialize, Serialize};
+
+/// Why a simulation failed, or didn't observe the changes it made
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorCategory {
+  Throttling,
+  ConditionalCheckFailure,
+  Validation,
+  Timeout,
+  Network,
+  Service,
+  TransactionConflict,
+  ConfirmationExhausted,
+  Other,
+}
+
+/// The classification of a failed DynamoDB call, attached as context to the SDK error so it survives being passed around
+/// as an `anyhow::Error`
+#[derive(Debug, Clone)]
+pub struct DynamoDbErrorDetails {
+  pub category: ErrorCategory,
+  pub code: Option<String>,
+  pub request_id: Option<String>,
+}
+
+impl fmt::Display for DynamoDbErrorDetails {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "DynamoDB call failed ({:?}, code: {}, request ID: {})",
+      self.category,
+      self.code.as_deref().unwrap_or("none"),
+      self.request_id.as_deref().unwrap_or("none")
+    )
+  }
+}
+
+/// Classifies a failed DynamoDB call by its error code, HTTP status, or the way the request failed
+pub fn classify_sdk_error<E>(error: SdkError<E>) -> anyhow::Error
+where
+  E: ProvideErrorMetadata + Error + Send + Sync + 'static,
+{
+  let code = error.code().map(str::to_owned);
+  let request_id = error.request_id().map(str::to_owned);
+  let category = match &error {
+    SdkError::TimeoutError(_) => ErrorCategory::Timeout,
+    SdkError::DispatchFailure(failure) if failure.is_timeout() => ErrorCategory::Timeout,
+    SdkError::DispatchFailure(_) | SdkError::ResponseError(_) => ErrorCategory::Network,
+    SdkError::ServiceError(service_error) => categorize_service_error(
+      code.as_deref(),
+      service_error.raw().http().status().as_u16(),
+    ),
+    _ => ErrorCategory::Other,
+  };
+
+  anyhow::Error::new(error).context(DynamoDbErrorDetails {
+    category,
+    code,
+    request_id,
+  })
+}
+
+/// `LimitExceededException` isn't request throttling: it's returned by the control plane when an account quota is reached
+/// (e.g. too many concurrent table operations), so it's left uncategorized
+fn categorize_service_error(code: Option<&str>, status: u16) -> ErrorCategory {
+  match code {
+    Some(
+      "ProvisionedThroughputExceededException" | "ThrottlingException" | "RequestLimitExceeded",
+    ) => ErrorCategory::Throttling,
+    Some("ConditionalCheckFailedException") => ErrorCategory::ConditionalCheckFailure,
+    Some("ValidationException" | "SerializationException") => ErrorCategory::Validation,
+    Some(
+      "TransactionConflictException"
+      | "TransactionCanceledException"
+      | "TransactionInProgressException",
+    ) => ErrorCategory::TransactionConflict,
+    _ if (500..600).contains(&status) => ErrorCategory::Service,
+    _ => ErrorCategory::Other,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn categorize_service_error_classifies_throttling_by_error_code() {
+    for code in [
+      "ProvisionedThroughputExceededException",
+      "ThrottlingException",
+      "RequestLimitExceeded",
+    ] {
+      assert_eq!(
+        categorize_service_error(Some(code), 400),
+        ErrorCategory::Throttling
+      );
+    }
+  }
+
+  #[test]
+  fn categorize_service_error_does_not_treat_quota_errors_as_throttling() {
+    assert_eq!(
+      categorize_service_error(Some("LimitExceededException"), 400),
+      ErrorCategory::Other
+    );
+  }
+
+  #[test]
+  fn categorize_service_error_falls_back_to_the_http_status() {
+    assert_eq!(
+      categorize_service_error(Some("InternalServerError"), 500),
+      ErrorCategory::Service
+    );
+    assert_eq!(categorize_service_error(None, 503), ErrorCategory::Service);
+    assert_eq!(categorize_service_error(None, 400), ErrorCategory::Other);
+  }
+}
@@ -1,13 +1,33 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::types::{AttributeValue, ScalarAttributeType};
 use chrono::{DateTime, Utc};
 use rand::Rng;
-use serde::Serialize;
-use serde_json::Number;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Number, Value};
 use uuid::Uuid;
 
-#[derive(Serialize, Debug, Default)]
+use crate::histograms::Phase;
+
+use self::errors::{DynamoDbErrorDetails, ErrorCategory};
+
+pub mod errors;
+pub mod run;
+
+/// The attribute that every item written during a simulation is tagged with, holding the ID of the run that wrote it
+pub const RUN_ID_ATTRIBUTE: &str = "runId";
+
+/// The name a unit-like value (e.g. a `Scenario`) is published under, such as `readOnly`, for use in labels and attributes
+pub fn serialized_name<T: Serialize>(value: &T) -> String {
+  match serde_json::to_value(value) {
+    Ok(Value::String(name)) => name,
+    _ => String::new(),
+  }
+}
+
+#[derive(
+  Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
 #[serde(rename_all = "camelCase")]
 pub enum DynamoOperation {
   #[default]
@@ -27,7 +47,7 @@ impl From<i32> for DynamoOperation {
   }
 }
 
-#[derive(Serialize, Debug, Default)]
+#[derive(Serialize, Debug, Default, Clone, Copy)]
 #[serde(rename_all = "camelCase")]
 pub enum Scenario {
   #[default]
@@ -67,6 +87,15 @@ impl BenchmarkingItem {
     BenchmarkingItem(benchmarking_item)
   }
 
+  /// The type of a generated attribute: even attributes are strings and odd attributes are numbers
+  pub fn attribute_type(attribute: u32) -> ScalarAttributeType {
+    if let 0 = attribute % 2 {
+      ScalarAttributeType::S
+    } else {
+      ScalarAttributeType::N
+    }
+  }
+
   pub fn get_id(&self) -> AttributeValue {
     self.0.get("id").cloned().unwrap()
   }
@@ -84,14 +113,86 @@ impl BenchmarkingItem {
   }
 }
 
+/// The unit that every latency in the metrics is recorded in
+#[derive(Serialize, Debug, Default, Clone, Copy)]
+pub enum TimeUnit {
+  #[default]
+  #[serde(rename = "us")]
+  Microseconds,
+}
+
+/// A single attempt the AWS SDK made to send a DynamoDB request
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestAttempt {
+  /// The DynamoDB request being sent, e.g. `GetItem`
+  pub request: &'static str,
+  /// The attempt number, starting at 1; every attempt after the first is a retry
+  pub attempt: u32,
+  pub latency: Number,
+  /// The HTTP status of the response, if one was received
+  pub status: Option<u16>,
+  /// Why the attempt failed (e.g. `ProvisionedThroughputExceededException` or `timeout`), which is why the SDK retried
+  /// it unless it was the last attempt
+  pub failure_reason: Option<String>,
+}
+
+/// How a single step of a simulation turned out
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum StepOutcome {
+  Succeeded,
+  /// The read succeeded, but didn't observe what the step was waiting for (the item, or the change made to it)
+  Unconfirmed,
+  Failed,
+}
+
+/// A single DynamoDB request made by a simulation
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationStep {
+  /// The phase of the simulation the request was made in, e.g. `writeConfirmation`
+  pub phase: Phase,
+  /// The DynamoDB request, e.g. `GetItem`
+  pub request: &'static str,
+  /// The attempt number within the phase, starting at 1; only reads and confirmations make more than one attempt
+  pub attempt: u32,
+  pub latency: Number,
+  pub outcome: StepOutcome,
+}
+
+/// A confirmation that polled for a change the simulation made, whether or not it observed the change
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Confirmation {
+  /// The phase of the simulation the confirmation was made in, e.g. `writeConfirmation`
+  pub phase: Phase,
+  /// The number of reads the confirmation made
+  pub attempts: u32,
+  /// Whether the confirmation gave up before observing the change
+  pub exhausted: bool,
+  /// The time spent confirming, including the delays between attempts
+  pub latency: Number,
+}
+
 #[derive(Serialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct DynamoDbSimulationMetrics {
+  pub run_id: String,
+  /// The labels the run was started with
+  pub labels: BTreeMap<String, String>,
   pub operation: DynamoOperation,
   pub timestamp: DateTime<Utc>,
   pub successful: bool,
   pub scenario: Scenario,
+  /// The unit of every latency below
+  pub time_unit: TimeUnit,
   pub simulation_time: Option<Number>,
+  /// The time from when the simulation was scheduled to start until it finished, which includes any time spent waiting
+  /// behind earlier simulations that ran long. Only recorded when the simulations are paced to a target rate
+  pub corrected_simulation_time: Option<Number>,
+  /// The time spent in each phase: the latency of the read's final attempt, and the total time of every other phase, including
+  /// every attempt made in it. The individual requests are in `steps`
   pub read_time: Option<Number>,
   pub write_time: Option<Number>,
   pub write_item_confirmation_time: Option<Number>,
@@ -99,4 +200,169 @@ pub struct DynamoDbSimulationMetrics {
   pub update_item_confirmation_time: Option<Number>,
   pub delete_time: Option<Number>,
   pub delete_item_confirmation_time: Option<Number>,
+  /// Every DynamoDB request the simulation made, in the order they were made
+  pub steps: Vec<SimulationStep>,
+  /// The total number of reads performed while confirming writes, updates, and deletes
+  pub confirmation_attempts: u32,
+  /// Every confirmation the simulation made, including the ones that were exhausted, in the order they were made
+  pub confirmations: Vec<Confirmation>,
+  /// The read capacity units consumed by every read in the simulation, including the reads made while confirming changes
+  pub read_capacity_units: f64,
+  /// The write capacity units consumed by every write, update, and delete in the simulation
+  pub write_capacity_units: f64,
+  /// The number of attempts the AWS SDK made across every DynamoDB request in the simulation, including retries
+  pub sdk_attempts: u32,
+  /// The number of those attempts that retried a failed attempt
+  pub sdk_retries: u32,
+  /// Every attempt the AWS SDK made, in the order they were made. Only the attempts of the requests that were retried are
+  /// published, since every request is already in `steps` and a simulation can make many of them
+  #[serde(serialize_with = "serialize_retried_attempts")]
+  pub request_attempts: Vec<RequestAttempt>,
+  /// Why the simulation failed, or `confirmationExhausted` if it succeeded but a confirmation gave up
+  pub error_category: Option<ErrorCategory>,
+  /// The error code returned by DynamoDB, when the simulation failed because of a DynamoDB error
+  pub error_code: Option<String>,
+  /// The ID of the failed DynamoDB request, for correlating with AWS support
+  pub request_id: Option<String>,
+}
+
+fn serialize_retried_attempts<S: Serializer>(
+  attempts: &[RequestAttempt],
+  serializer: S,
+) -> Result<S::Ok, S::Error> {
+  serializer.collect_seq(retried_attempts(attempts))
+}
+
+/// The attempts of every request that needed more than one attempt
+fn retried_attempts(attempts: &[RequestAttempt]) -> impl Iterator<Item = &RequestAttempt> {
+  attempts
+    .iter()
+    .enumerate()
+    .filter(|(i, attempt)| {
+      attempt.attempt > 1 || attempts.get(i + 1).is_some_and(|next| next.attempt > 1)
+    })
+    .map(|(_, attempt)| attempt)
+}
+
+impl DynamoDbSimulationMetrics {
+  /// Every attempt that retried a failed attempt, along with the reason the previous attempt failed
+  pub fn retries(&self) -> impl Iterator<Item = (&RequestAttempt, &str)> {
+    self
+      .request_attempts
+      .windows(2)
+      .filter(|attempts| attempts[1].attempt > 1)
+      .map(|attempts| {
+        (
+          &attempts[1],
+          attempts[0].failure_reason.as_deref().unwrap_or("unknown"),
+        )
+      })
+  }
+
+  pub fn record_step(
+    &mut self,
+    phase: Phase,
+    request: &'static str,
+    attempt: u32,
+    latency: Number,
+    outcome: StepOutcome,
+  ) {
+    self.steps.push(SimulationStep {
+      phase,
+      request,
+      attempt,
+      latency,
+      outcome,
+    });
+  }
+
+  /// Marks the latest step as unconfirmed, when its read succeeded but didn't observe what it was waiting for
+  pub fn unconfirm_last_step(&mut self) {
+    if let Some(step) = self.steps.last_mut() {
+      step.outcome = StepOutcome::Unconfirmed;
+    }
+  }
+
+  /// The number of confirmations that gave up before observing the expected change
+  pub fn exhausted_confirmations(&self) -> u32 {
+    self
+      .confirmations
+      .iter()
+      .filter(|confirmation| confirmation.exhausted)
+      .count() as u32
+  }
+
+  /// Records why the simulation failed, or whether any of its confirmations gave up
+  pub fn record_outcome(&mut self, error: Option<&anyhow::Error>) {
+    match error {
+      Some(error) => {
+        let details = error.downcast_ref::<DynamoDbErrorDetails>();
+        self.error_category =
+          Some(details.map_or(ErrorCategory::Other, |details| details.category));
+        self.error_code = details.and_then(|details| details.code.clone());
+        self.request_id = details.and_then(|details| details.request_id.clone());
+      }
+      None => {
+        self.successful = true;
+        if self.exhausted_confirmations() > 0 {
+          self.error_category = Some(ErrorCategory::ConfirmationExhausted);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn attempt(request: &'static str, attempt: u32) -> RequestAttempt {
+    RequestAttempt {
+      request,
+      attempt,
+      latency: Number::from(1000),
+      status: Some(200),
+      failure_reason: None,
+    }
+  }
+
+  #[test]
+  fn only_the_attempts_of_retried_requests_are_published() {
+    let metrics = DynamoDbSimulationMetrics {
+      request_attempts: vec![
+        attempt("PutItem", 1),
+        attempt("GetItem", 1),
+        attempt("GetItem", 2),
+        attempt("GetItem", 3),
+        attempt("DeleteItem", 1),
+        attempt("GetItem", 1),
+        attempt("GetItem", 2),
+      ],
+      ..DynamoDbSimulationMetrics::default()
+    };
+
+    let published = serde_json::to_value(&metrics).unwrap()["requestAttempts"]
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|attempt| {
+        (
+          attempt["request"].as_str().unwrap().to_owned(),
+          attempt["attempt"].as_u64().unwrap(),
+        )
+      })
+      .collect::<Vec<(String, u64)>>();
+
+    assert_eq!(
+      published,
+      [
+        (String::from("GetItem"), 1),
+        (String::from("GetItem"), 2),
+        (String::from("GetItem"), 3),
+        (String::from("GetItem"), 1),
+        (String::from("GetItem"), 2),
+      ]
+    );
+    assert_eq!(metrics.retries().count(), 3);
+  }
 }
@@ -0,0 +1,36 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Identifies a benchmark run and how it was configured. It's published once when the run starts, while its run ID and
+/// labels are stamped on every metric
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RunMetadata {
+  pub run_id: String,
+  pub started_at: DateTime<Utc>,
+  pub hostname: String,
+  /// The version of the `dynamodb-benchmarker` that ran the benchmark
+  pub version: &'static str,
+  pub labels: BTreeMap<String, String>,
+  /// The effective configuration of the run, including every default that wasn't overridden on the command line
+  pub config: Value,
+}
+
+impl RunMetadata {
+  pub fn new(labels: &[(String, String)], config: Value) -> RunMetadata {
+    RunMetadata {
+      run_id: Uuid::new_v4().to_string(),
+      started_at: Utc::now(),
+      hostname: hostname::get()
+        .map(|hostname| hostname.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| String::from("unknown")),
+      version: env!("CARGO_PKG_VERSION"),
+      labels: labels.iter().cloned().collect(),
+      config,
+    }
+  }
+}
@@ -0,0 +1,330 @@
+use std::{
+  fs,
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex},
+};
+
+use anyhow::anyhow;
+use aws_sdk_dynamodb::Client;
+use chrono::{DateTime, Utc};
+use clap::Args;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{error, info, warn};
+use rand::{
+  rngs::{OsRng, StdRng},
+  seq::SliceRandom,
+  Rng, SeedableRng,
+};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinSet;
+
+#[derive(Args, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartitionKeyArgs {
+  /// The number of parallel segments to scan the table with when fetching partition keys to read
+  #[arg(long, default_value_t = 8)]
+  scan_segments: u32,
+  /// Keep a uniform random sample of at most this many partition keys to read instead of every key in the table
+  #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+  key_sample_size: Option<usize>,
+  /// A file to cache the scanned partition keys in; subsequent runs reuse the cached keys instead of scanning the table
+  #[arg(long)]
+  key_cache_file: Option<PathBuf>,
+  /// The fraction by which the table's item count may drift from the cached item count before the key cache is considered stale
+  #[arg(long, default_value_t = 0.1)]
+  key_cache_tolerance: f64,
+  /// Always reuse the key cache file, regardless of staleness, so runs read the exact same set of keys
+  #[arg(long, requires = "key_cache_file")]
+  pin_key_cache: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PartitionKeyCache {
+  table_name: String,
+  item_count: Option<i64>,
+  /// The sample size the keys were scanned with, if they're a sample rather than every key in the table
+  sample_size: Option<usize>,
+  created_at: DateTime<Utc>,
+  partition_keys: Vec<String>,
+}
+
+impl PartitionKeyCache {
+  fn load(path: &Path) -> anyhow::Result<PartitionKeyCache> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+  }
+
+  fn save(&self, path: &Path) -> anyhow::Result<()> {
+    fs::write(path, serde_json::to_string(self)?)?;
+    Ok(())
+  }
+
+  fn is_stale(&self, item_count: Option<i64>, tolerance: f64) -> bool {
+    match (self.item_count, item_count) {
+      (Some(cached_item_count), Some(item_count)) => {
+        let drift = (item_count - cached_item_count).abs() as f64 / cached_item_count.max(1) as f64;
+        drift > tolerance
+      }
+      _ => false,
+    }
+  }
+
+  /// Whether the cached keys are at least as large a sample as the one requested, so they can stand in for a fresh scan
+  fn covers(&self, sample_size: Option<usize>) -> bool {
+    match (self.sample_size, sample_size) {
+      (None, _) => true,
+      (Some(_), None) => false,
+      (Some(cached_sample_size), Some(sample_size)) => cached_sample_size >= sample_size,
+    }
+  }
+
+  /// The cached keys, narrowed down to a uniform random sample of the requested size when the cache holds more
+  fn into_partition_keys(self, sample_size: Option<usize>) -> Vec<String> {
+    match sample_size {
+      Some(sample_size) if self.partition_keys.len() > sample_size => {
+        let mut rng = StdRng::from_seed(OsRng.gen());
+        self
+          .partition_keys
+          .choose_multiple(&mut rng, sample_size)
+          .cloned()
+          .collect()
+      }
+      _ => self.partition_keys,
+    }
+  }
+}
+
+/// Holds either every key offered to it, or a uniform random sample of them when a capacity is set
+struct KeyReservoir {
+  capacity: Option<usize>,
+  seen: u64,
+  keys: Vec<String>,
+  rng: StdRng,
+}
+
+impl KeyReservoir {
+  fn new(capacity: Option<usize>) -> KeyReservoir {
+    KeyReservoir {
+      capacity,
+      seen: 0,
+      keys: Vec::with_capacity(capacity.unwrap_or_default()),
+      rng: StdRng::from_seed(OsRng.gen()),
+    }
+  }
+
+  fn offer(&mut self, key: String) {
+    self.seen += 1;
+    match self.capacity {
+      Some(capacity) if self.keys.len() >= capacity => {
+        let index = self.rng.gen_range(0..self.seen);
+        if index < capacity as u64 {
+          self.keys[index as usize] = key;
+        }
+      }
+      _ => self.keys.push(key),
+    }
+  }
+}
+
+/// Fetches the partition keys to read, either from the key cache file when it's present and fresh, or by scanning the table
+pub async fn fetch_partition_keys(
+  dynamodb_client: &Client,
+  table_name: &str,
+  args: &PartitionKeyArgs,
+) -> anyhow::Result<Vec<String>> {
+  let Some(key_cache_file) = &args.key_cache_file else {
+    return scan_all_partition_keys(
+      dynamodb_client,
+      table_name,
+      args.scan_segments,
+      args.key_sample_size,
+    )
+    .await;
+  };
+
+  let cache = match PartitionKeyCache::load(key_cache_file) {
+    Ok(cache) => Some(cache),
+    Err(e) if key_cache_file.exists() => {
+      warn!(
+        "Unable to read the key cache file {}: {e:?}",
+        key_cache_file.display()
+      );
+      None
+    }
+    Err(_) => None,
+  };
+
+  let cache = cache.filter(|cache| cache.table_name == table_name);
+  if args.pin_key_cache {
+    return match cache {
+      Some(cache) => {
+        let cached_keys = cache.partition_keys.len();
+        let partition_keys = cache.into_partition_keys(args.key_sample_size);
+        info!(
+          "Using {} of the {cached_keys} pinned partition keys from {}",
+          partition_keys.len(),
+          key_cache_file.display()
+        );
+        Ok(partition_keys)
+      }
+      None => Err(anyhow!(
+        "Unable to use the pinned key cache file {}: it does not contain keys for {table_name}",
+        key_cache_file.display()
+      )),
+    };
+  }
+
+  let item_count = fetch_item_count(dynamodb_client, table_name).await;
+  if let Some(cache) = cache {
+    if !cache.covers(args.key_sample_size) {
+      info!(
+        "The key cache file {} holds a smaller sample of partition keys than was requested",
+        key_cache_file.display()
+      );
+    } else if cache.is_stale(item_count, args.key_cache_tolerance) {
+      info!(
+        "The key cache file {} is stale; the table's item count changed from {:?} to {:?}",
+        key_cache_file.display(),
+        cache.item_count,
+        item_count
+      );
+    } else {
+      info!(
+        "Using {} cached partition keys from {} created at {}",
+        cache.partition_keys.len(),
+        key_cache_file.display(),
+        cache.created_at
+      );
+      return Ok(cache.into_partition_keys(args.key_sample_size));
+    }
+  }
+
+  let partition_keys = scan_all_partition_keys(
+    dynamodb_client,
+    table_name,
+    args.scan_segments,
+    args.key_sample_size,
+  )
+  .await?;
+
+  info!(
+    "Caching {} partition keys to {}",
+    partition_keys.len(),
+    key_cache_file.display()
+  );
+  let cache = PartitionKeyCache {
+    table_name: table_name.to_owned(),
+    item_count,
+    sample_size: args.key_sample_size,
+    created_at: Utc::now(),
+    partition_keys,
+  };
+  if let Err(e) = cache.save(key_cache_file) {
+    warn!(
+      "Unable to write the key cache file {}: {e:?}",
+      key_cache_file.display()
+    );
+  }
+
+  Ok(cache.partition_keys)
+}
+
+/// The approximate number of items in the table, as reported by DynamoDB roughly every six hours
+async fn fetch_item_count(dynamodb_client: &Client, table_name: &str) -> Option<i64> {
+  match dynamodb_client
+    .describe_table()
+    .table_name(table_name)
+    .send()
+    .await
+  {
+    Ok(resp) => resp.table().and_then(|table| table.item_count()),
+    Err(e) => {
+      warn!("Unable to fetch the item count of {table_name}: {e:?}");
+      None
+    }
+  }
+}
+
+/// Scans the entire table for its partition keys using a parallel segmented scan that follows pagination.
+/// When a `sample_size` is given, a uniform random sample of at most that many keys is returned instead
+async fn scan_all_partition_keys(
+  dynamodb_client: &Client,
+  table_name: &str,
+  segments: u32,
+  sample_size: Option<usize>,
+) -> anyhow::Result<Vec<String>> {
+  info!("Fetching partition keys from {table_name} using {segments} parallel scan segments...");
+  let reservoir = Arc::new(Mutex::new(KeyReservoir::new(sample_size)));
+  let progress_bar = ProgressBar::new_spinner().with_style(ProgressStyle::with_template(
+    "{spinner} [{elapsed_precise}] Scanned {pos} partition keys ({per_sec})",
+  )?);
+
+  let mut segment_scanners = JoinSet::new();
+  for segment in 0..segments.max(1) {
+    let dynamodb_client = dynamodb_client.clone();
+    let table_name = table_name.to_owned();
+    let reservoir = reservoir.clone();
+    let progress_bar = progress_bar.clone();
+
+    segment_scanners.spawn(async move {
+      let mut exclusive_start_key = None;
+      loop {
+        let response = dynamodb_client
+          .scan()
+          .table_name(&table_name)
+          .projection_expression("id")
+          .segment(segment as i32)
+          .total_segments(segments.max(1) as i32)
+          .set_exclusive_start_key(exclusive_start_key)
+          .send()
+          .await;
+
+        match response {
+          Ok(resp) => {
+            let page_keys = resp
+              .items()
+              .unwrap_or_default()
+              .iter()
+              .filter_map(|item| item.get("id").and_then(|id| id.as_s().ok()).cloned())
+              .collect::<Vec<String>>();
+            progress_bar.inc(page_keys.len() as u64);
+
+            let mut reservoir = reservoir.lock().unwrap();
+            page_keys.into_iter().for_each(|key| reservoir.offer(key));
+            drop(reservoir);
+
+            exclusive_start_key = resp.last_evaluated_key().cloned();
+            if exclusive_start_key.is_none() {
+              return Ok(());
+            }
+          }
+          Err(e) => {
+            error!("Unable to scan segment {segment} for partition keys! {e:?}");
+            return Err(anyhow!(e));
+          }
+        }
+      }
+    });
+  }
+
+  while let Some(result) = segment_scanners.join_next().await {
+    result??;
+  }
+  progress_bar.finish_and_clear();
+
+  let reservoir = Arc::try_unwrap(reservoir)
+    .map_err(|_| anyhow!("Partition key reservoir is still in use"))?
+    .into_inner()?;
+  let partition_keys = reservoir.keys;
+  info!(
+    "Scanned a total of {} keys and kept {} of them",
+    reservoir.seen,
+    partition_keys.len()
+  );
+
+  if partition_keys.is_empty() {
+    return Err(anyhow!("No partition keys were found in {table_name}"));
+  }
+
+  Ok(partition_keys)
+}
@@ -1,7 +1,16 @@
+use std::time::Duration;
+
 use aws_sdk_dynamodb::types::AttributeValue;
 use log::{error, info};
+use opentelemetry::trace::SpanKind;
+use serde_json::Number;
 
-use crate::{models::DynamoDbSimulationMetrics, time};
+use crate::{
+  histograms::Phase,
+  models::{BenchmarkingItem, Confirmation, DynamoDbSimulationMetrics},
+  telemetry::in_span,
+  time,
+};
 
 use super::{utils, Simulator};
 
@@ -11,61 +20,120 @@ impl<'a> Simulator<'a> {
     id: AttributeValue,
     metrics: &mut DynamoDbSimulationMetrics,
   ) -> anyhow::Result<()> {
-    let partition_key = utils::extract_partition_key(id.clone());
-    let mut attempts_exhausted = false;
-
-    let write_confirmation_time = time!(for i in 0..10 {
-      info!("Attempt {i}: Fetching newly added item with partition key: {partition_key}");
-
-      match self.read_item(id.clone(), metrics, false).await? {
-        Some(_) => {
-          info!("Successfully read new item with partition key: {partition_key}");
-          break;
-        }
-        None => {
-          error!("Unable to find new item with partition key: {partition_key}");
-          if i == 9 {
-            error!("All attempts to fetch the newly added item with partition key: {partition_key} failed!");
-            attempts_exhausted = true;
-          }
-        }
-      };
-    });
+    self
+      .confirm(
+        "ConfirmWrite",
+        Phase::WriteConfirmation,
+        id,
+        metrics,
+        |item| item.is_some(),
+      )
+      .await
+  }
 
-    if !attempts_exhausted {
-      metrics.write_item_confirmation_time = Some(write_confirmation_time);
-    }
+  pub(super) async fn assert_item_was_updated(
+    &mut self,
+    id: AttributeValue,
+    original_item: &BenchmarkingItem,
+    metrics: &mut DynamoDbSimulationMetrics,
+  ) -> anyhow::Result<()> {
+    let attribute =
+      |item: &BenchmarkingItem| item.get("1").and_then(|value| value.as_n().ok()).cloned();
+    let original_value = attribute(original_item);
 
-    Ok(())
+    self
+      .confirm(
+        "ConfirmUpdate",
+        Phase::UpdateConfirmation,
+        id,
+        metrics,
+        |item| item.is_some_and(|item| attribute(item) != original_value),
+      )
+      .await
   }
 
   pub(super) async fn assert_item_was_deleted(
     &mut self,
     id: AttributeValue,
     metrics: &mut DynamoDbSimulationMetrics,
+  ) -> anyhow::Result<()> {
+    self
+      .confirm(
+        "ConfirmDelete",
+        Phase::DeleteConfirmation,
+        id,
+        metrics,
+        |item| item.is_none(),
+      )
+      .await
+  }
+
+  /// Reads the item until `is_confirmed` observes the change the simulation made, or the configured attempts run out.
+  /// The confirmation is recorded on the metrics either way, but its time, less the delays between attempts, is only
+  /// recorded as the phase's latency if the change was confirmed
+  async fn confirm(
+    &mut self,
+    span_name: &'static str,
+    phase: Phase,
+    id: AttributeValue,
+    metrics: &mut DynamoDbSimulationMetrics,
+    is_confirmed: impl Fn(Option<&BenchmarkingItem>) -> bool,
   ) -> anyhow::Result<()> {
     let partition_key = utils::extract_partition_key(id.clone());
-    let mut attempts_exhausted = false;
-    let delete_confirmation_time = time!(for i in 0..10 {
-      info!("Attempt {i}: Fetching deleted item with partition key: {partition_key}...");
-      match self.read_item(id.clone(), metrics, false).await? {
-        Some(_) => {
-          error!("Item with partition key {partition_key} was not deleted as expected!");
-          if i == 9 {
-            error!("All attempts to receive an empty response to verify item with partition key: {partition_key} was deleted failed!");
-            attempts_exhausted = true;
-          }
-        }
-        None => {
-          info!("Item with partition key {partition_key} was successfully deleted.");
+    let attempts = self.context.confirmation.confirmation_attempts;
+    let mut confirmation = Confirmation {
+      phase,
+      attempts: 0,
+      exhausted: false,
+      latency: 0.into(),
+    };
+
+    let mut delayed = Duration::ZERO;
+    let latency = in_span(span_name, SpanKind::Internal, Vec::new(), async {
+      Ok::<_, anyhow::Error>(time!(for attempt in 1..=attempts {
+        metrics.confirmation_attempts += 1;
+        confirmation.attempts = attempt;
+        info!("Attempt {attempt}: Confirming {phase:?} for partition key: {partition_key}");
+
+        let item = self.read_item(id.clone(), metrics, phase, attempt).await?;
+        if is_confirmed(item.as_ref()) {
+          info!("Confirmed {phase:?} for partition key: {partition_key}");
           break;
         }
-      }
-    });
 
-    if !attempts_exhausted {
-      metrics.delete_item_confirmation_time = Some(delete_confirmation_time);
+        metrics.unconfirm_last_step();
+        if attempt == attempts {
+          error!("Exhausted all {attempts} attempts to confirm {phase:?} for partition key: {partition_key}!");
+          confirmation.exhausted = true;
+        } else {
+          error!("Unable to confirm {phase:?} for partition key: {partition_key}");
+          let delay = self.context.confirmation.delay(attempt, &mut self.rng);
+          if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+            delayed += delay;
+          }
+        }
+      }))
+    })
+    .await?;
+
+    if !confirmation.exhausted {
+      // The phase's latency is the time spent reading, so the configured delays don't skew its percentiles
+      let reading_time = Number::from(
+        latency
+          .as_u64()
+          .unwrap_or_default()
+          .saturating_sub(delayed.as_micros() as u64),
+      );
+      match phase {
+        Phase::WriteConfirmation => metrics.write_item_confirmation_time = Some(reading_time),
+        Phase::UpdateConfirmation => metrics.update_item_confirmation_time = Some(reading_time),
+        Phase::DeleteConfirmation => metrics.delete_item_confirmation_time = Some(reading_time),
+        _ => (),
+      }
     }
+    confirmation.latency = latency;
+    metrics.confirmations.push(confirmation);
 
     Ok(())
   }
@@ -1,36 +1,114 @@
-use aws_sdk_dynamodb::{types::AttributeValue, Client};
+use std::{collections::HashSet, sync::Mutex, time::Duration};
+
+use aws_sdk_dynamodb::{
+  types::{AttributeValue, ReturnConsumedCapacity},
+  Client,
+};
+use clap::{Args, ValueEnum};
 use log::{error, info};
 use rand::{
   rngs::{OsRng, StdRng},
   Rng, SeedableRng,
 };
+use serde::Serialize;
 
-use crate::{models::DynamoDbSimulationMetrics, time};
+use crate::{histograms::Phase, models::DynamoDbSimulationMetrics, summary::SimulationStats};
 
 mod assertions;
 mod operations;
 mod utils;
 
+/// How much detail DynamoDB returns about the capacity consumed by each call: either only the total, or also the capacity
+/// consumed by each index the call touched
+#[derive(ValueEnum, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConsumedCapacityDetail {
+  Total,
+  Indexes,
+}
+
+impl From<ConsumedCapacityDetail> for ReturnConsumedCapacity {
+  fn from(value: ConsumedCapacityDetail) -> ReturnConsumedCapacity {
+    match value {
+      ConsumedCapacityDetail::Total => ReturnConsumedCapacity::Total,
+      ConsumedCapacityDetail::Indexes => ReturnConsumedCapacity::Indexes,
+    }
+  }
+}
+
+/// How long to wait between the attempts a confirmation makes to observe a change
+#[derive(ValueEnum, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConfirmationBackoff {
+  Fixed,
+  Exponential,
+  Jittered,
+}
+
+/// How confirmations poll for the changes a simulation made
+#[derive(Args, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmationArgs {
+  /// The number of times to read an item when confirming a write, update, or delete before giving up on the confirmation
+  #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+  confirmation_attempts: u32,
+  /// The delay, in milliseconds, between the attempts a confirmation makes
+  #[arg(long, default_value_t = 0)]
+  confirmation_delay: u64,
+  /// How the delay between attempts grows: a fixed delay, an exponential delay that doubles after every attempt, or an
+  /// exponential delay with full random jitter
+  #[arg(long, value_enum, default_value_t = ConfirmationBackoff::Fixed)]
+  confirmation_backoff: ConfirmationBackoff,
+  /// The maximum delay, in milliseconds, between attempts when backing off exponentially
+  #[arg(long, default_value_t = 1000)]
+  confirmation_max_delay: u64,
+}
+
+impl ConfirmationArgs {
+  /// How long to wait after the given (1-based) attempt failed to confirm a change, before trying again
+  fn delay(&self, attempt: u32, rng: &mut StdRng) -> Duration {
+    let delay = Duration::from_millis(self.confirmation_delay);
+    let max_delay = Duration::from_millis(self.confirmation_max_delay);
+    let exponential = || {
+      delay
+        .saturating_mul(2u32.saturating_pow(attempt - 1))
+        .min(max_delay)
+    };
+
+    match self.confirmation_backoff {
+      ConfirmationBackoff::Fixed => delay,
+      ConfirmationBackoff::Exponential => exponential(),
+      // "Full jitter": a uniformly random delay of up to the exponential delay, which spreads out simulations that are
+      // confirming at the same time
+      ConfirmationBackoff::Jittered => exponential().mul_f64(rng.gen()),
+    }
+  }
+}
+
+/// The state shared by every simulation task for the duration of a run
+pub struct SimulationContext {
+  pub dynamodb_client: Client,
+  pub table_name: String,
+  pub consumed_capacity_detail: ConsumedCapacityDetail,
+  pub attributes: u32,
+  pub partition_keys: Vec<String>,
+  pub confirmation: ConfirmationArgs,
+  pub run_id: String,
+  /// The partition keys of items that have been written but not yet deleted, so they can be cleaned up if the run is cancelled
+  pub in_flight_items: Mutex<HashSet<String>>,
+  /// The stats of every simulation task, merged together as each task finishes
+  pub simulation_stats: Mutex<SimulationStats>,
+}
+
 pub struct Simulator<'a> {
-  dynamodb_client: &'a Client,
-  table_name: String,
-  attributes: u32,
-  partition_keys_vec: &'a [String],
+  context: &'a SimulationContext,
   rng: StdRng,
 }
 
 impl<'a> Simulator<'a> {
-  pub fn new(
-    dynamodb_client: &'a Client,
-    table_name: String,
-    attributes: u32,
-    partition_keys_vec: &'a [String],
-  ) -> Simulator<'a> {
+  pub fn new(context: &'a SimulationContext) -> Simulator<'a> {
     Simulator {
-      dynamodb_client,
-      table_name,
-      attributes,
-      partition_keys_vec,
+      context,
       rng: StdRng::from_seed(OsRng.gen()),
     }
   }
@@ -40,20 +118,24 @@ impl<'a> Simulator<'a> {
     metrics: &mut DynamoDbSimulationMetrics,
   ) -> anyhow::Result<()> {
     info!("Performing READ Operation...");
-    let partition_key =
-      self.partition_keys_vec[self.rng.gen_range(0..self.partition_keys_vec.len())].clone();
+    let partition_keys = &self.context.partition_keys;
+    let partition_key = partition_keys[self.rng.gen_range(0..partition_keys.len())].clone();
     let id = AttributeValue::S(partition_key.clone());
 
     for i in 0..10 {
       info!("Attempt {i}: Fetching existing item with partition key: {partition_key}");
 
-      match self.read_item(id.clone(), metrics, true).await? {
+      match self
+        .read_item(id.clone(), metrics, Phase::Read, i + 1)
+        .await?
+      {
         Some(_) => {
           info!("Successfully read existing item with partition key: {partition_key}");
           break;
         }
         None => {
           error!("Unable to find existing item with partition key: {partition_key}");
+          metrics.unconfirm_last_step();
           if i == 9 {
             error!(
               "All attempts to fetch the existing item with partition key: {partition_key} failed!"
@@ -62,6 +144,12 @@ impl<'a> Simulator<'a> {
         }
       }
     }
+    // The read's latency is that of its final attempt; every attempt, including the ones that missed the item, is in the steps
+    metrics.read_time = metrics
+      .steps
+      .last()
+      .filter(|step| step.phase == Phase::Read)
+      .map(|step| step.latency.clone());
 
     Ok(())
   }
@@ -90,47 +178,13 @@ impl<'a> Simulator<'a> {
     info!("Performing UPDATE operation...");
     let new_item = self.put_item(metrics).await?;
     let id = new_item.get_id();
-    let partition_key = utils::extract_partition_key(id.clone());
-    let mut attempts_exhausted = false;
 
     self.assert_item_was_created(id.clone(), metrics).await?;
     self.update_item(id.clone(), metrics).await?;
 
-    let update_confirmation_time = time!(for i in 0..10 {
-      info!("Attempt {i}: Fetching updated item for partition key: {partition_key}...");
-
-      let updated_item = self.read_item(id.clone(), metrics, false).await?.unwrap();
-
-      let new_item_attribute_value = new_item
-        .get("1")
-        .cloned()
-        .unwrap()
-        .as_n()
-        .unwrap()
-        .to_string();
-      let updated_item_attribute_value = updated_item
-        .get("1")
-        .cloned()
-        .unwrap()
-        .as_n()
-        .unwrap()
-        .to_string();
-
-      if new_item_attribute_value != updated_item_attribute_value {
-        info!("Confirmed update for partition key: {partition_key}");
-        break;
-      } else {
-        error!("Update for partition key {partition_key} failed! Values are still equal!");
-        if i == 9 {
-          error!("Exhausted attempts to fetch updated item!");
-          attempts_exhausted = true;
-        }
-      }
-    });
-
-    if !attempts_exhausted {
-      metrics.update_item_confirmation_time = Some(update_confirmation_time);
-    }
+    self
+      .assert_item_was_updated(id.clone(), &new_item, metrics)
+      .await?;
 
     self.delete_item(id.clone(), metrics).await?;
     self.assert_item_was_deleted(id, metrics).await?;
@@ -1,39 +1,71 @@
-use anyhow::anyhow;
-use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::types::{AttributeValue, ConsumedCapacity};
 use log::{error, info};
 
 use crate::{
-  models::{BenchmarkingItem, DynamoDbSimulationMetrics},
+  attempts::record_attempts,
+  histograms::Phase,
+  models::{
+    errors::classify_sdk_error, BenchmarkingItem, DynamoDbSimulationMetrics, StepOutcome,
+    RUN_ID_ATTRIBUTE,
+  },
+  telemetry::in_dynamodb_span,
   time,
 };
 
 use super::{utils::extract_partition_key, Simulator};
 
 impl<'a> Simulator<'a> {
+  /// Generates a new item tagged with the current run's ID, so it can be found and cleaned up later if it's orphaned
+  fn new_item(&self) -> BenchmarkingItem {
+    let mut item = BenchmarkingItem::new(self.context.attributes);
+    item.insert(
+      RUN_ID_ATTRIBUTE,
+      AttributeValue::S(self.context.run_id.clone()),
+    );
+    item
+  }
+
+  /// Reads an item as the given attempt of a phase of the simulation. The read is recorded as a successful step whether or
+  /// not the item was found; it's up to the caller to mark it unconfirmed if it didn't observe what the caller expected
   pub async fn read_item(
     &mut self,
     id: AttributeValue,
     metrics: &mut DynamoDbSimulationMetrics,
-    record_metrics: bool,
+    phase: Phase,
+    attempt: u32,
   ) -> anyhow::Result<Option<BenchmarkingItem>> {
     let partition_key = extract_partition_key(id.clone());
     let (read_time, response) = time!(
       resp,
-      self
-        .dynamodb_client
-        .get_item()
-        .table_name(self.table_name.clone())
-        .key("id", id)
-        .send()
-        .await
+      record_attempts(
+        "GetItem",
+        metrics,
+        in_dynamodb_span(
+          "GetItem",
+          &self.context.table_name,
+          self
+            .context
+            .dynamodb_client
+            .get_item()
+            .table_name(&self.context.table_name)
+            .return_consumed_capacity(self.context.consumed_capacity_detail.into())
+            .key("id", id)
+            .send()
+        )
+      )
+      .await
+    );
+    metrics.record_step(
+      phase,
+      "GetItem",
+      attempt,
+      read_time,
+      step_outcome(&response),
     );
-
-    if record_metrics {
-      metrics.read_time = Some(read_time);
-    }
 
     match response {
       Ok(resp) => {
+        metrics.read_capacity_units += consumed_read_capacity(resp.consumed_capacity());
         info!("Found item: {}", partition_key);
         if let Some(item) = resp.item() {
           info!("Fetched item: {item:?}");
@@ -45,7 +77,7 @@ impl<'a> Simulator<'a> {
       }
       Err(e) => {
         error!("Could not fetch item with partition key: {partition_key}. {e:?}");
-        Err(anyhow!(e))
+        Err(classify_sdk_error(e))
       }
     }
   }
@@ -55,29 +87,47 @@ impl<'a> Simulator<'a> {
     id: AttributeValue,
     metrics: &mut DynamoDbSimulationMetrics,
   ) -> anyhow::Result<()> {
-    let mut updated_item = BenchmarkingItem::new(self.attributes);
+    let mut updated_item = self.new_item();
     updated_item.insert("id", id.clone());
     let partition_key = extract_partition_key(id);
     let (update_time, response) = time!(
       resp,
-      self
-        .dynamodb_client
-        .put_item()
-        .table_name(self.table_name.clone())
-        .set_item(Some(updated_item.extract_map()))
-        .send()
-        .await
+      record_attempts(
+        "PutItem",
+        metrics,
+        in_dynamodb_span(
+          "PutItem",
+          &self.context.table_name,
+          self
+            .context
+            .dynamodb_client
+            .put_item()
+            .table_name(&self.context.table_name)
+            .return_consumed_capacity(self.context.consumed_capacity_detail.into())
+            .set_item(Some(updated_item.extract_map()))
+            .send()
+        )
+      )
+      .await
+    );
+    metrics.update_time = Some(update_time.clone());
+    metrics.record_step(
+      Phase::Update,
+      "PutItem",
+      1,
+      update_time,
+      step_outcome(&response),
     );
-    metrics.update_time = Some(update_time);
 
     match response {
-      Ok(_) => {
+      Ok(resp) => {
+        metrics.write_capacity_units += consumed_write_capacity(resp.consumed_capacity());
         info!("Successfully updated item with partition_key: {partition_key}");
         Ok(())
       }
       Err(e) => {
         error!("Could not update item with partition key: {partition_key}. {e:?}");
-        Err(anyhow!(e))
+        Err(classify_sdk_error(e))
       }
     }
   }
@@ -86,28 +136,46 @@ impl<'a> Simulator<'a> {
     &mut self,
     metrics: &mut DynamoDbSimulationMetrics,
   ) -> anyhow::Result<BenchmarkingItem> {
-    let new_item = BenchmarkingItem::new(self.attributes);
+    let new_item = self.new_item();
     let partition_key = extract_partition_key(new_item.get("id").cloned().unwrap());
+    self
+      .context
+      .in_flight_items
+      .lock()
+      .unwrap()
+      .insert(partition_key.clone());
     let (time, response) = time!(
       resp,
-      self
-        .dynamodb_client
-        .put_item()
-        .table_name(self.table_name.clone())
-        .set_item(Some(new_item.extract_map()))
-        .send()
-        .await
+      record_attempts(
+        "PutItem",
+        metrics,
+        in_dynamodb_span(
+          "PutItem",
+          &self.context.table_name,
+          self
+            .context
+            .dynamodb_client
+            .put_item()
+            .table_name(&self.context.table_name)
+            .return_consumed_capacity(self.context.consumed_capacity_detail.into())
+            .set_item(Some(new_item.extract_map()))
+            .send()
+        )
+      )
+      .await
     );
-    metrics.write_time = Some(time);
+    metrics.write_time = Some(time.clone());
+    metrics.record_step(Phase::Write, "PutItem", 1, time, step_outcome(&response));
 
     match response {
-      Ok(_) => {
+      Ok(resp) => {
+        metrics.write_capacity_units += consumed_write_capacity(resp.consumed_capacity());
         info!("Successfully put new item with partition key: {partition_key}");
         Ok(new_item)
       }
       Err(e) => {
         error!("Could not put new item with partition key: {partition_key}. {e:?}");
-        Err(anyhow!(e))
+        Err(classify_sdk_error(e))
       }
     }
   }
@@ -120,25 +188,75 @@ impl<'a> Simulator<'a> {
     let partition_key = extract_partition_key(id.clone());
     let (delete_time, response) = time!(
       resp,
-      self
-        .dynamodb_client
-        .delete_item()
-        .table_name(self.table_name.clone())
-        .key("id", id)
-        .send()
-        .await
+      record_attempts(
+        "DeleteItem",
+        metrics,
+        in_dynamodb_span(
+          "DeleteItem",
+          &self.context.table_name,
+          self
+            .context
+            .dynamodb_client
+            .delete_item()
+            .table_name(&self.context.table_name)
+            .return_consumed_capacity(self.context.consumed_capacity_detail.into())
+            .key("id", id)
+            .send()
+        )
+      )
+      .await
+    );
+    metrics.delete_time = Some(delete_time.clone());
+    metrics.record_step(
+      Phase::Delete,
+      "DeleteItem",
+      1,
+      delete_time,
+      step_outcome(&response),
     );
-    metrics.delete_time = Some(delete_time);
 
     match response {
-      Ok(_) => {
+      Ok(resp) => {
+        metrics.write_capacity_units += consumed_write_capacity(resp.consumed_capacity());
         info!("Successfully deleted item with partition key: {partition_key}");
+        self
+          .context
+          .in_flight_items
+          .lock()
+          .unwrap()
+          .remove(&partition_key);
         Ok(())
       }
       Err(e) => {
         error!("Could not delete item with partition key: {partition_key}. {e:?}");
-        Err(anyhow!(e))
+        Err(classify_sdk_error(e))
       }
     }
   }
 }
+
+fn step_outcome<T, E>(response: &Result<T, E>) -> StepOutcome {
+  if response.is_ok() {
+    StepOutcome::Succeeded
+  } else {
+    StepOutcome::Failed
+  }
+}
+
+/// The read capacity units consumed by a call, falling back to the total when DynamoDB doesn't break them out
+fn consumed_read_capacity(capacity: Option<&ConsumedCapacity>) -> f64 {
+  capacity
+    .and_then(|capacity| capacity.read_capacity_units().or(capacity.capacity_units()))
+    .unwrap_or_default()
+}
+
+/// The write capacity units consumed by a call, falling back to the total when DynamoDB doesn't break them out
+fn consumed_write_capacity(capacity: Option<&ConsumedCapacity>) -> f64 {
+  capacity
+    .and_then(|capacity| {
+      capacity
+        .write_capacity_units()
+        .or(capacity.capacity_units())
+    })
+    .unwrap_or_default()
+}
@@ -0,0 +1,246 @@
+use anyhow::anyhow;
+use async_trait::async_trait;
+use elasticsearch::{
+  auth::Credentials,
+  http::{
+    transport::{SingleNodeConnectionPool, TransportBuilder},
+    Url,
+  },
+  indices::{IndicesCreateParts, IndicesExistsParts, IndicesPutMappingParts},
+  Elasticsearch, IndexParts,
+};
+use log::info;
+use serde_json::{json, Value};
+
+use crate::models::{run::RunMetadata, DynamoDbSimulationMetrics};
+
+use super::{
+  windows::{WindowAggregator, WindowedMetrics},
+  MetricsSink, PublishMode,
+};
+
+/// Creates a client for the Elasticsearch cluster at the given URL, authenticating with basic auth
+pub fn elasticsearch_client(
+  url: &str,
+  username: String,
+  password: String,
+) -> anyhow::Result<Elasticsearch> {
+  let url = Url::parse(url)?;
+  let connection_pool = SingleNodeConnectionPool::new(url);
+  let credentials = Credentials::Basic(username, password);
+  let transport = TransportBuilder::new(connection_pool)
+    .auth(credentials)
+    .build()?;
+
+  Ok(Elasticsearch::new(transport))
+}
+
+/// Sets the explicit mappings of the index, creating it with them if it doesn't exist yet. The mappings must be in place before
+/// the first document is indexed, or the fields are dynamically mapped (e.g. `runId` as analyzed text, which breaks looking runs up)
+async fn put_mappings(
+  es_client: &Elasticsearch,
+  index: &str,
+  properties: Value,
+) -> anyhow::Result<()> {
+  info!("Setting the explicit mappings for the {index} index");
+  let exists = es_client
+    .indices()
+    .exists(IndicesExistsParts::Index(&[index]))
+    .send()
+    .await?
+    .status_code()
+    .is_success();
+
+  let es_response = if exists {
+    es_client
+      .indices()
+      .put_mapping(IndicesPutMappingParts::Index(&[index]))
+      .body(json!({ "properties": properties }))
+      .send()
+      .await?
+  } else {
+    es_client
+      .indices()
+      .create(IndicesCreateParts::Index(index))
+      .body(json!({ "mappings": { "properties": properties } }))
+      .send()
+      .await?
+  };
+
+  if es_response.status_code().is_success() {
+    Ok(())
+  } else {
+    Err(anyhow!(
+      "Was unable to set the mappings of the {index} index! Received {}: {}",
+      es_response.status_code(),
+      es_response.text().await.unwrap_or_default()
+    ))
+  }
+}
+
+pub struct ElasticsearchSink {
+  es_client: Elasticsearch,
+  index: String,
+  /// Aggregates the metrics into time windows before they're published, when publishing aggregated metrics
+  aggregator: Option<WindowAggregator>,
+}
+
+impl ElasticsearchSink {
+  pub async fn new(
+    url: &str,
+    username: String,
+    password: String,
+    index: String,
+    mode: PublishMode,
+    window_seconds: u64,
+  ) -> anyhow::Result<ElasticsearchSink> {
+    let es_client = elasticsearch_client(url, username, password)?;
+
+    let aggregator = match mode {
+      PublishMode::Raw => None,
+      PublishMode::Aggregated => Some(WindowAggregator::new(window_seconds)),
+    };
+    match aggregator {
+      Some(_) => {
+        put_mappings(
+          &es_client,
+          &format!("windows-{index}"),
+          json!({
+            "timestamp": {
+              "type": "date"
+            },
+            "runId": {
+              "type": "keyword"
+            },
+            "operation": {
+              "type": "keyword"
+            },
+            "timeUnit": {
+              "type": "keyword"
+            }
+          }),
+        )
+        .await?
+      }
+      None => {
+        put_mappings(
+          &es_client,
+          &index,
+          json!({
+            "timestamp": {
+              "type": "date"
+            },
+            "runId": {
+              "type": "keyword"
+            },
+            "timeUnit": {
+              "type": "keyword"
+            },
+            "errorCategory": {
+              "type": "keyword"
+            },
+            "errorCode": {
+              "type": "keyword"
+            },
+            "requestId": {
+              "type": "keyword"
+            }
+          }),
+        )
+        .await?
+      }
+    }
+
+    Ok(ElasticsearchSink {
+      es_client,
+      index,
+      aggregator,
+    })
+  }
+
+  /// Indexes every closed window into the `windows-<index>` index
+  async fn publish_windows(&self, windows: Vec<WindowedMetrics>) -> anyhow::Result<()> {
+    let windows_index = format!("windows-{}", self.index);
+    for window in windows {
+      info!(
+        "Publishing the {:?} metrics of the window starting at {} to Elasticsearch...",
+        window.operation, window.timestamp
+      );
+      let es_response = self
+        .es_client
+        .index(IndexParts::Index(&windows_index))
+        .body(&window)
+        .send()
+        .await?;
+
+      if !es_response.status_code().is_success() {
+        return Err(anyhow!(
+          "Was unable to publish windowed metrics to Elasticsearch! Received non 2XX response"
+        ));
+      }
+    }
+
+    Ok(())
+  }
+}
+
+#[async_trait]
+impl MetricsSink for ElasticsearchSink {
+  fn name(&self) -> &str {
+    "Elasticsearch"
+  }
+
+  /// Indexes the run into the `runs-<index>` index, with the run ID as the document ID
+  async fn publish_run(&mut self, run: &RunMetadata) -> anyhow::Result<()> {
+    let runs_index = format!("runs-{}", self.index);
+    let es_response = self
+      .es_client
+      .index(IndexParts::IndexId(&runs_index, &run.run_id))
+      .body(run)
+      .send()
+      .await?;
+
+    if es_response.status_code().is_success() {
+      info!("Published run {} to the {runs_index} index", run.run_id);
+      Ok(())
+    } else {
+      Err(anyhow!(
+        "Was unable to publish the run to Elasticsearch! Received non 2XX response"
+      ))
+    }
+  }
+
+  async fn publish(&mut self, metrics: &DynamoDbSimulationMetrics) -> anyhow::Result<()> {
+    if let Some(aggregator) = self.aggregator.as_mut() {
+      let windows = aggregator.record(metrics);
+      return self.publish_windows(windows).await;
+    }
+
+    info!("Publishing metrics to Elasticsearch...");
+
+    let es_response = self
+      .es_client
+      .index(IndexParts::Index(&self.index))
+      .body(metrics)
+      .send()
+      .await?;
+
+    if es_response.status_code().is_success() {
+      info!("Successfully published metrics to Elasticsearch");
+      Ok(())
+    } else {
+      Err(anyhow!(
+        "Was unable to publish metrics to Elasticsearch! Received non 2XX response"
+      ))
+    }
+  }
+
+  async fn close(&mut self) -> anyhow::Result<()> {
+    if let Some(aggregator) = self.aggregator.as_mut() {
+      let windows = aggregator.finish();
+      self.publish_windows(windows).await?;
+    }
+
+    Ok(())
+  }
+}
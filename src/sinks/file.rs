@@ -0,0 +1,442 @@
+use std::{
+  fs::{self, File},
+  io::{self, BufWriter, Write},
+  path::{Path, PathBuf},
+  time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use log::info;
+use serde_json::Value;
+
+use crate::models::{run::RunMetadata, DynamoDbSimulationMetrics};
+
+use super::MetricsSink;
+
+/// When a file sink should close the file it's writing to and start a new one
+#[derive(Default, Clone, Copy)]
+pub struct FileRotation {
+  pub max_bytes: Option<u64>,
+  pub max_age: Option<Duration>,
+}
+
+impl FileRotation {
+  fn is_enabled(&self) -> bool {
+    self.max_bytes.is_some() || self.max_age.is_some()
+  }
+}
+
+/// Serializes metrics into a single file of a particular format
+pub trait MetricsFileWriter: Send + Sized {
+  /// The name of the file format, for use in log messages
+  const FORMAT: &'static str;
+
+  fn create(path: &Path) -> anyhow::Result<Self>;
+
+  fn write(&mut self, metrics: &DynamoDbSimulationMetrics) -> anyhow::Result<()>;
+
+  /// The approximate size of the file so far, including any output that's still buffered
+  fn bytes_written(&self) -> u64;
+
+  /// Flushes any buffered output and finalizes the file
+  fn finish(self) -> anyhow::Result<()>;
+}
+
+/// Writes every metric to local files, optionally rotating to a new file once the current one
+/// grows too large or has been open for too long.
+///
+/// Without rotation, metrics are written to the given path as-is. With rotation, each file is numbered
+/// (e.g. `metrics.jsonl` becomes `metrics-00000.jsonl`, `metrics-00001.jsonl`, ...)
+pub struct FileSink<W: MetricsFileWriter> {
+  path: PathBuf,
+  rotation: FileRotation,
+  files_opened: u32,
+  opened_at: Instant,
+  writer: Option<W>,
+}
+
+impl<W: MetricsFileWriter> FileSink<W> {
+  pub fn new(path: &Path, rotation: FileRotation) -> anyhow::Result<FileSink<W>> {
+    let mut sink = FileSink {
+      path: path.to_owned(),
+      rotation,
+      files_opened: 0,
+      opened_at: Instant::now(),
+      writer: None,
+    };
+    sink.open_next_file()?;
+
+    Ok(sink)
+  }
+
+  fn open_next_file(&mut self) -> anyhow::Result<&mut W> {
+    let path = if self.rotation.is_enabled() {
+      numbered_path(&self.path, self.files_opened)
+    } else {
+      self.path.clone()
+    };
+
+    info!("Writing metrics as {} to {}", W::FORMAT, path.display());
+    let writer = W::create(&path)?;
+    self.files_opened += 1;
+    self.opened_at = Instant::now();
+
+    Ok(self.writer.insert(writer))
+  }
+
+  fn should_rotate(&self, writer: &W) -> bool {
+    let too_large = self
+      .rotation
+      .max_bytes
+      .is_some_and(|max_bytes| writer.bytes_written() >= max_bytes);
+    let too_old = self
+      .rotation
+      .max_age
+      .is_some_and(|max_age| self.opened_at.elapsed() >= max_age);
+
+    too_large || too_old
+  }
+}
+
+#[async_trait]
+impl<W: MetricsFileWriter> MetricsSink for FileSink<W> {
+  fn name(&self) -> &str {
+    W::FORMAT
+  }
+
+  /// Writes the run to a JSON file alongside the metrics (e.g. `metrics.parquet.run.json` for `metrics.parquet`)
+  async fn publish_run(&mut self, run: &RunMetadata) -> anyhow::Result<()> {
+    let path = run_metadata_path(&self.path);
+    fs::write(&path, serde_json::to_string_pretty(run)?)?;
+    info!("Wrote the run metadata to {}", path.display());
+    Ok(())
+  }
+
+  async fn publish(&mut self, metrics: &DynamoDbSimulationMetrics) -> anyhow::Result<()> {
+    if self
+      .writer
+      .as_ref()
+      .is_some_and(|writer| self.should_rotate(writer))
+    {
+      self.close().await?;
+    }
+
+    let writer = match self.writer {
+      Some(ref mut writer) => writer,
+      None => self.open_next_file()?,
+    };
+    writer.write(metrics)
+  }
+
+  async fn close(&mut self) -> anyhow::Result<()> {
+    if let Some(writer) = self.writer.take() {
+      writer.finish()?;
+    }
+
+    Ok(())
+  }
+}
+
+/// The files a file sink wrote its metrics to: either the file itself, or every numbered file if the output was rotated
+pub fn metrics_files(path: &Path) -> Vec<PathBuf> {
+  if path.exists() {
+    return vec![path.to_owned()];
+  }
+
+  (0..)
+    .map(|number| numbered_path(path, number))
+    .take_while(|path| path.exists())
+    .collect()
+}
+
+/// The file the run's metadata is written to beside a file sink's metrics. It's named after the full file name, so sinks
+/// writing to files that only differ by extension (e.g. `metrics.jsonl` and `metrics.csv`) don't overwrite each other's
+pub fn run_metadata_path(path: &Path) -> PathBuf {
+  let file_name = path
+    .file_name()
+    .map(|file_name| file_name.to_string_lossy().into_owned())
+    .unwrap_or_default();
+  path.with_file_name(format!("{file_name}.run.json"))
+}
+
+/// Inserts the file number between the file's stem and extension
+fn numbered_path(path: &Path, number: u32) -> PathBuf {
+  let stem = path
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().into_owned())
+    .unwrap_or_default();
+  let file_name = match path.extension() {
+    Some(extension) => format!("{stem}-{number:05}.{}", extension.to_string_lossy()),
+    None => format!("{stem}-{number:05}"),
+  };
+
+  path.with_file_name(file_name)
+}
+
+/// A buffered file writer that keeps track of how many bytes have been written through it
+pub struct CountingWriter {
+  writer: BufWriter<File>,
+  bytes_written: u64,
+}
+
+impl CountingWriter {
+  pub fn create(path: &Path) -> anyhow::Result<CountingWriter> {
+    Ok(CountingWriter {
+      writer: BufWriter::new(File::create(path)?),
+      bytes_written: 0,
+    })
+  }
+}
+
+impl Write for CountingWriter {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    let written = self.writer.write(buf)?;
+    self.bytes_written += written as u64;
+    Ok(written)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.writer.flush()
+  }
+}
+
+/// Writes every metric as a single line of JSON
+pub struct JsonLinesWriter {
+  writer: CountingWriter,
+}
+
+impl MetricsFileWriter for JsonLinesWriter {
+  const FORMAT: &'static str = "JSON lines";
+
+  fn create(path: &Path) -> anyhow::Result<JsonLinesWriter> {
+    Ok(JsonLinesWriter {
+      writer: CountingWriter::create(path)?,
+    })
+  }
+
+  fn write(&mut self, metrics: &DynamoDbSimulationMetrics) -> anyhow::Result<()> {
+    serde_json::to_writer(&mut self.writer, metrics)?;
+    self.writer.write_all(b"\n")?;
+    Ok(())
+  }
+
+  fn bytes_written(&self) -> u64 {
+    self.writer.bytes_written
+  }
+
+  fn finish(mut self) -> anyhow::Result<()> {
+    self.writer.flush()?;
+    Ok(())
+  }
+}
+
+/// Writes every metric as a CSV row, with one column per metric field
+pub struct CsvWriter {
+  writer: csv::Writer<CountingWriter>,
+  header_written: bool,
+}
+
+impl MetricsFileWriter for CsvWriter {
+  const FORMAT: &'static str = "CSV";
+
+  fn create(path: &Path) -> anyhow::Result<CsvWriter> {
+    Ok(CsvWriter {
+      writer: csv::Writer::from_writer(CountingWriter::create(path)?),
+      header_written: false,
+    })
+  }
+
+  fn write(&mut self, metrics: &DynamoDbSimulationMetrics) -> anyhow::Result<()> {
+    let columns = flatten_metrics(metrics)?;
+    if !self.header_written {
+      self
+        .writer
+        .write_record(columns.iter().map(|(name, _)| name))?;
+      self.header_written = true;
+    }
+
+    self
+      .writer
+      .write_record(columns.iter().map(|(_, value)| value))?;
+    Ok(())
+  }
+
+  fn bytes_written(&self) -> u64 {
+    self.writer.get_ref().bytes_written
+  }
+
+  fn finish(mut self) -> anyhow::Result<()> {
+    self.writer.flush()?;
+    Ok(())
+  }
+}
+
+/// Flattens the metrics into (field name, value) pairs. Nested values are kept as JSON strings
+fn flatten_metrics(metrics: &DynamoDbSimulationMetrics) -> anyhow::Result<Vec<(String, String)>> {
+  let Value::Object(fields) = serde_json::to_value(metrics)? else {
+    unreachable!("Metrics always serialize to a JSON object");
+  };
+
+  Ok(
+    fields
+      .into_iter()
+      .map(|(name, value)| {
+        let value = match value {
+          Value::Null => String::new(),
+          Value::String(value) => value,
+          value => value.to_string(),
+        };
+        (name, value)
+      })
+      .collect(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use std::env;
+
+  use chrono::{DateTime, Utc};
+  use serde_json::Number;
+  use uuid::Uuid;
+
+  use super::*;
+  use crate::{
+    commands::recorded::{load_metrics_file, RecordedMetrics},
+    histograms::Phase,
+    models::{errors::ErrorCategory, Confirmation, DynamoOperation, RequestAttempt, StepOutcome},
+    sinks::parquet::ParquetWriter,
+  };
+
+  /// Metrics with every field populated, so any field a format can't hold fails the round trip
+  fn metrics(successful: bool) -> DynamoDbSimulationMetrics {
+    let attempt = |attempt: u32, status: u16, failure_reason: Option<&str>| RequestAttempt {
+      request: "PutItem",
+      attempt,
+      latency: Number::from(800),
+      status: Some(status),
+      failure_reason: failure_reason.map(str::to_owned),
+    };
+    let mut metrics = DynamoDbSimulationMetrics {
+      run_id: String::from("run"),
+      labels: [(String::from("env"), String::from("staging, \"blue\""))].into(),
+      operation: DynamoOperation::Write,
+      timestamp: DateTime::from_timestamp(1_700_000_000, 123_000).unwrap(),
+      successful,
+      simulation_time: Some(Number::from(5000)),
+      corrected_simulation_time: Some(Number::from(6000)),
+      write_time: Some(Number::from(1600)),
+      write_item_confirmation_time: Some(Number::from(900)),
+      confirmation_attempts: 2,
+      confirmations: vec![Confirmation {
+        phase: Phase::WriteConfirmation,
+        attempts: 2,
+        exhausted: !successful,
+        latency: Number::from(950),
+      }],
+      read_capacity_units: 1.0,
+      write_capacity_units: 1.0,
+      sdk_attempts: 4,
+      sdk_retries: 1,
+      request_attempts: vec![
+        attempt(1, 400, Some("ThrottlingException")),
+        attempt(2, 200, None),
+      ],
+      error_category: (!successful).then_some(ErrorCategory::Throttling),
+      error_code: (!successful).then(|| String::from("ThrottlingException")),
+      request_id: (!successful).then(|| String::from("request")),
+      ..DynamoDbSimulationMetrics::default()
+    };
+    metrics.record_step(
+      Phase::Write,
+      "PutItem",
+      1,
+      Number::from(1600),
+      StepOutcome::Succeeded,
+    );
+    metrics.record_step(
+      Phase::WriteConfirmation,
+      "GetItem",
+      1,
+      Number::from(400),
+      StepOutcome::Unconfirmed,
+    );
+    metrics
+  }
+
+  /// The fields of recorded metrics that compare and report read
+  type RecordedFields = (
+    DynamoOperation,
+    DateTime<Utc>,
+    bool,
+    Vec<(Phase, f64)>,
+    Option<ErrorCategory>,
+    Option<String>,
+  );
+
+  fn recorded_fields(metrics: &RecordedMetrics) -> RecordedFields {
+    (
+      metrics.operation,
+      metrics.timestamp,
+      metrics.successful,
+      metrics.latencies().collect(),
+      metrics.error_category,
+      metrics.error_code.clone(),
+    )
+  }
+
+  async fn assert_round_trips<W: MetricsFileWriter>(extension: &str) {
+    let path = env::temp_dir().join(format!("metrics-{}.{extension}", Uuid::new_v4()));
+    let written = [metrics(true), metrics(false)];
+    let mut sink = FileSink::<W>::new(&path, FileRotation::default()).unwrap();
+    for metrics in &written {
+      sink.publish(metrics).await.unwrap();
+    }
+    sink.close().await.unwrap();
+
+    let mut read = Vec::new();
+    let result = load_metrics_file(&path, &mut |metrics| read.push(metrics));
+    fs::remove_file(&path).unwrap();
+    result.unwrap();
+
+    let expected = written
+      .iter()
+      .map(|metrics| {
+        let json = serde_json::to_value(metrics).unwrap();
+        recorded_fields(&serde_json::from_value(json).unwrap())
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      read.iter().map(recorded_fields).collect::<Vec<_>>(),
+      expected,
+      "{extension}"
+    );
+  }
+
+  #[tokio::test]
+  async fn json_lines_metrics_can_be_read_back() {
+    assert_round_trips::<JsonLinesWriter>("jsonl").await;
+  }
+
+  #[tokio::test]
+  async fn csv_metrics_can_be_read_back() {
+    assert_round_trips::<CsvWriter>("csv").await;
+  }
+
+  #[tokio::test]
+  async fn parquet_metrics_can_be_read_back() {
+    assert_round_trips::<ParquetWriter>("parquet").await;
+  }
+
+  #[test]
+  fn run_metadata_is_named_after_the_full_file_name() {
+    assert_eq!(
+      run_metadata_path(Path::new("out/metrics.jsonl")),
+      Path::new("out/metrics.jsonl.run.json")
+    );
+    assert_ne!(
+      run_metadata_path(Path::new("metrics.jsonl")),
+      run_metadata_path(Path::new("metrics.csv"))
+    );
+  }
+}
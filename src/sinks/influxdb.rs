@@ -0,0 +1,262 @@
+use std::net::UdpSocket;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use log::info;
+use reqwest::{header::AUTHORIZATION, Client, Url};
+
+use crate::{
+  histograms::Phase,
+  models::{serialized_name, DynamoDbSimulationMetrics},
+};
+
+use super::{MetricTags, MetricsSink};
+
+const MEASUREMENT: &str = "dynamodb_benchmarker";
+
+/// The number of lines sent to InfluxDB in a single HTTP write request
+const LINES_PER_WRITE: usize = 500;
+
+enum Transport {
+  Http {
+    client: Client,
+    url: Url,
+    token: Option<String>,
+  },
+  Udp(UdpSocket),
+}
+
+/// Translates every metric into a single line of InfluxDB line protocol, sent over HTTP in batches or over UDP one line at a time
+pub struct InfluxDbSink {
+  transport: Transport,
+  tags: MetricTags,
+  lines: Vec<String>,
+}
+
+impl InfluxDbSink {
+  /// The URL is either the full HTTP write URL (e.g. `http://localhost:8086/api/v2/write?org=my-org&bucket=benchmarks`
+  /// or `http://localhost:8086/write?db=benchmarks`), or the address of a UDP listener (e.g. `udp://localhost:8089`)
+  pub fn new(url: &str, token: Option<String>, tags: MetricTags) -> anyhow::Result<InfluxDbSink> {
+    let url = Url::parse(url)?;
+    let transport = match url.scheme() {
+      "http" | "https" => Transport::Http {
+        client: Client::new(),
+        url: url.clone(),
+        token,
+      },
+      "udp" => {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(format!(
+          "{}:{}",
+          url
+            .host_str()
+            .ok_or_else(|| anyhow!("The InfluxDB URL {url} has no host"))?,
+          url.port().unwrap_or(8089)
+        ))?;
+        Transport::Udp(socket)
+      }
+      scheme => return Err(anyhow!("Unsupported InfluxDB URL scheme: {scheme}")),
+    };
+
+    info!("Writing metrics as InfluxDB line protocol to {url}");
+    Ok(InfluxDbSink {
+      transport,
+      tags,
+      lines: Vec::new(),
+    })
+  }
+
+  fn to_line(&self, metrics: &DynamoDbSimulationMetrics) -> String {
+    let mut line = MEASUREMENT.to_owned();
+    for (key, value) in self.tags.values(metrics) {
+      line.push_str(&format!(",{key}={}", escape_tag(&value)));
+    }
+
+    line.push_str(&format!(
+      " successful={},confirmation_attempts={}i,exhausted_confirmations={}i,read_capacity_units={:?},write_capacity_units={:?},sdk_attempts={}i,sdk_retries={}i,time_unit=\"{}\"",
+      metrics.successful,
+      metrics.confirmation_attempts,
+      metrics.exhausted_confirmations(),
+      metrics.read_capacity_units,
+      metrics.write_capacity_units,
+      metrics.sdk_attempts,
+      metrics.sdk_retries,
+      serialized_name(&metrics.time_unit)
+    ));
+    if let Some(category) = &metrics.error_category {
+      line.push_str(&format!(
+        ",error_category=\"{}\"",
+        serialized_name(category)
+      ));
+    }
+    if let Some(code) = &metrics.error_code {
+      line.push_str(&format!(",error_code=\"{}\"", escape_string(code)));
+    }
+    for (phase, latency) in Phase::latencies(metrics) {
+      if let Some(latency) = latency.as_f64() {
+        line.push_str(&format!(",{}={latency:?}", serialized_name(&phase)));
+      }
+    }
+
+    if let Some(timestamp) = metrics.timestamp.timestamp_nanos_opt() {
+      line.push_str(&format!(" {timestamp}"));
+    }
+
+    line
+  }
+
+  async fn flush(&mut self) -> anyhow::Result<()> {
+    if self.lines.is_empty() {
+      return Ok(());
+    }
+
+    let body = self.lines.join("\n");
+    self.lines.clear();
+    if let Transport::Http { client, url, token } = &self.transport {
+      let mut request = client.post(url.clone()).body(body);
+      if let Some(token) = token {
+        request = request.header(AUTHORIZATION, format!("Token {token}"));
+      }
+      request.send().await?.error_for_status()?;
+    }
+
+    Ok(())
+  }
+}
+
+#[async_trait]
+impl MetricsSink for InfluxDbSink {
+  fn name(&self) -> &str {
+    "InfluxDB"
+  }
+
+  async fn publish(&mut self, metrics: &DynamoDbSimulationMetrics) -> anyhow::Result<()> {
+    let line = self.to_line(metrics);
+    match &self.transport {
+      Transport::Udp(socket) => {
+        socket.send(line.as_bytes())?;
+      }
+      Transport::Http { .. } => {
+        self.lines.push(line);
+        if self.lines.len() >= LINES_PER_WRITE {
+          self.flush().await?;
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  async fn close(&mut self) -> anyhow::Result<()> {
+    self.flush().await
+  }
+}
+
+/// Escapes the characters that are significant in line protocol tag values
+fn escape_tag(value: &str) -> String {
+  value
+    .replace('\\', "\\\\")
+    .replace(',', "\\,")
+    .replace('=', "\\=")
+    .replace(' ', "\\ ")
+}
+
+/// Escapes the characters that are significant in line protocol string field values
+fn escape_string(value: &str) -> String {
+  value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use chrono::DateTime;
+  use serde_json::Number;
+
+  use super::*;
+  use crate::{
+    models::{errors::ErrorCategory, DynamoOperation},
+    sinks::MetricTag,
+  };
+
+  fn sink() -> InfluxDbSink {
+    InfluxDbSink::new(
+      "udp://127.0.0.1:8089",
+      None,
+      MetricTags {
+        tags: vec![MetricTag::Operation, MetricTag::Table],
+        table_name: String::from("high velocity,table"),
+      },
+    )
+    .unwrap()
+  }
+
+  fn metrics() -> DynamoDbSimulationMetrics {
+    DynamoDbSimulationMetrics {
+      operation: DynamoOperation::Write,
+      timestamp: DateTime::from_timestamp(1_700_000_000, 5).unwrap(),
+      labels: [(String::from("env"), String::from("a=b c"))].into(),
+      simulation_time: Some(Number::from(1500)),
+      ..DynamoDbSimulationMetrics::default()
+    }
+  }
+
+  #[test]
+  fn to_line_escapes_tag_values() {
+    let line = sink().to_line(&metrics());
+
+    assert!(
+      line.starts_with(
+        r"dynamodb_benchmarker,operation=write,table=high\ velocity\,table,env=a\=b\ c successful=false,"
+      ),
+      "{line}"
+    );
+    assert!(line.contains(",simulation=1500.0"), "{line}");
+    assert!(line.ends_with(" 1700000000000000005"), "{line}");
+  }
+
+  #[test]
+  fn to_line_escapes_string_fields() {
+    let line = sink().to_line(&DynamoDbSimulationMetrics {
+      error_category: Some(ErrorCategory::Validation),
+      error_code: Some(String::from(r#"Bad\"Code""#)),
+      ..metrics()
+    });
+
+    assert!(
+      line.contains(r#",error_category="validation",error_code="Bad\\\"Code\"""#),
+      "{line}"
+    );
+  }
+
+  #[tokio::test]
+  async fn publish_sends_each_line_over_udp() {
+    let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+    listener
+      .set_read_timeout(Some(Duration::from_secs(5)))
+      .unwrap();
+    let mut sink = InfluxDbSink::new(
+      &format!("udp://{}", listener.local_addr().unwrap()),
+      None,
+      MetricTags {
+        tags: vec![MetricTag::Operation],
+        table_name: String::from("table"),
+      },
+    )
+    .unwrap();
+
+    for metrics in [metrics(), metrics()] {
+      sink.publish(&metrics).await.unwrap();
+    }
+    sink.close().await.unwrap();
+
+    let mut buffer = [0; 4096];
+    for _ in 0..2 {
+      let received = listener.recv(&mut buffer).unwrap();
+      assert_eq!(
+        std::str::from_utf8(&buffer[..received]).unwrap(),
+        sink.to_line(&metrics())
+      );
+    }
+  }
+}
@@ -0,0 +1,316 @@
+use std::{
+  net::SocketAddr,
+  path::PathBuf,
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+  },
+  time::Duration,
+};
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+use log::{error, info};
+use serde::Serialize;
+use tokio::sync::mpsc::Receiver;
+
+use crate::models::{run::RunMetadata, serialized_name, DynamoDbSimulationMetrics};
+
+use self::{
+  elasticsearch::ElasticsearchSink,
+  file::{CsvWriter, FileRotation, FileSink, JsonLinesWriter},
+  influxdb::InfluxDbSink,
+  otlp::{OtlpProtocol, OtlpSink},
+  parquet::ParquetWriter,
+  prometheus::PrometheusSink,
+  statsd::StatsDSink,
+  stdout::StdoutSink,
+};
+
+pub use self::{
+  elasticsearch::elasticsearch_client,
+  file::{metrics_files, run_metadata_path},
+};
+
+mod elasticsearch;
+mod file;
+mod influxdb;
+mod otlp;
+mod parquet;
+mod prometheus;
+mod statsd;
+mod stdout;
+mod windows;
+
+/// A destination that every simulation's metrics are published to
+#[async_trait]
+pub trait MetricsSink: Send {
+  /// The name of the sink, for use in log messages
+  fn name(&self) -> &str;
+
+  /// Called once before any metrics are published, for the sinks that keep a record of each run
+  async fn publish_run(&mut self, _run: &RunMetadata) -> anyhow::Result<()> {
+    Ok(())
+  }
+
+  async fn publish(&mut self, metrics: &DynamoDbSimulationMetrics) -> anyhow::Result<()>;
+
+  /// Called once every metric has been published, so any buffered output can be flushed
+  async fn close(&mut self) -> anyhow::Result<()> {
+    Ok(())
+  }
+}
+
+/// Live gauges of the run's progress that sinks may report alongside the metrics themselves
+#[derive(Default)]
+pub struct RunGauges {
+  /// The number of simulation tasks currently running
+  pub active_simulations: AtomicUsize,
+  /// The number of metrics waiting in the channel to be published
+  pub queued_metrics: AtomicUsize,
+}
+
+#[derive(ValueEnum, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum SinkKind {
+  Elasticsearch,
+  JsonLines,
+  Csv,
+  Parquet,
+  Prometheus,
+  Otlp,
+  Influxdb,
+  Statsd,
+  Stdout,
+  None,
+}
+
+/// Whether every simulation's metrics are published individually, or aggregated into one document per operation per time window
+#[derive(ValueEnum, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum PublishMode {
+  Raw,
+  Aggregated,
+}
+
+#[derive(ValueEnum, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum MetricTag {
+  Scenario,
+  Operation,
+  Table,
+  RunId,
+}
+
+/// The tags attached to every metric by the sinks that support tagging, followed by the run's labels
+pub struct MetricTags {
+  tags: Vec<MetricTag>,
+  table_name: String,
+}
+
+impl MetricTags {
+  pub fn values<'a>(&'a self, metrics: &'a DynamoDbSimulationMetrics) -> Vec<(&'a str, String)> {
+    self
+      .tags
+      .iter()
+      .map(|tag| match tag {
+        MetricTag::Scenario => ("scenario", serialized_name(&metrics.scenario)),
+        MetricTag::Operation => ("operation", serialized_name(&metrics.operation)),
+        MetricTag::Table => ("table", self.table_name.clone()),
+        MetricTag::RunId => ("run_id", metrics.run_id.clone()),
+      })
+      .chain(
+        metrics
+          .labels
+          .iter()
+          .map(|(key, value)| (key.as_str(), value.clone())),
+      )
+      .collect()
+  }
+}
+
+#[derive(Args, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SinkArgs {
+  /// Where to publish metrics to; may be specified multiple times to publish to several sinks at once
+  #[arg(short, long = "sink", value_enum, default_values_t = [SinkKind::Elasticsearch])]
+  sinks: Vec<SinkKind>,
+  /// The buffer size of the metrics publisher thread's MPSC channel
+  #[arg(short, long, default_value_t = 500)]
+  pub buffer: usize,
+  /// The URL of the Elasticsearch cluster to publish metrics to
+  #[arg(long, default_value_t = String::from("http://localhost:9200"))]
+  elasticsearch_url: String,
+  /// Local Elasticsearch cluster username
+  #[arg(short, long, default_value_t = String::from("elastic"))]
+  username: String,
+  /// Local Elasticsearch cluster password
+  #[arg(short, long, default_value_t = String::from("changeme"))]
+  #[serde(skip)]
+  password: String,
+  /// The Elasticsearch Index to insert data into
+  #[arg(short, long, default_value_t = String::from("dynamodb"))]
+  index: String,
+  /// How to publish metrics to Elasticsearch: one document per simulation, or, for long runs, one document per operation per
+  /// time window (with counts, errors and latency percentiles) in the `windows-<index>` index
+  #[arg(long, value_enum, default_value_t = PublishMode::Raw)]
+  elasticsearch_mode: PublishMode,
+  /// The length of each time window, in seconds, when publishing aggregated metrics to Elasticsearch
+  #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+  aggregation_window: u64,
+  /// The file to write metrics to when using the json-lines sink
+  #[arg(long, default_value = "metrics.jsonl")]
+  json_lines_file: PathBuf,
+  /// The file to write metrics to when using the csv sink
+  #[arg(long, default_value = "metrics.csv")]
+  csv_file: PathBuf,
+  /// The file to write metrics to when using the parquet sink
+  #[arg(long, default_value = "metrics.parquet")]
+  parquet_file: PathBuf,
+  /// The address to serve the Prometheus /metrics endpoint on when using the prometheus sink
+  #[arg(long, default_value = "0.0.0.0:9898")]
+  prometheus_address: SocketAddr,
+  /// The OTLP collector endpoint to export spans and metrics to when using the otlp sink.
+  /// Defaults to http://localhost:4317 for gRPC and http://localhost:4318 for HTTP
+  #[arg(long)]
+  otlp_endpoint: Option<String>,
+  /// The protocol to export spans and metrics with when using the otlp sink
+  #[arg(long, value_enum, default_value_t = OtlpProtocol::Grpc)]
+  otlp_protocol: OtlpProtocol,
+  /// The URL to write metrics to when using the influxdb sink; either a full HTTP write URL
+  /// (e.g. http://localhost:8086/api/v2/write?org=my-org&bucket=benchmarks) or a UDP listener (e.g. udp://localhost:8089)
+  #[arg(long, default_value_t = String::from("http://localhost:8086/write?db=dynamodb"))]
+  influxdb_url: String,
+  /// The API token to authenticate with when writing to InfluxDB over HTTP
+  #[arg(long)]
+  #[serde(skip)]
+  influxdb_token: Option<String>,
+  /// The address of the StatsD agent to send metrics to when using the statsd sink
+  #[arg(long, default_value_t = String::from("localhost:8125"))]
+  statsd_address: String,
+  /// The tags to attach to every metric sent to the influxdb and statsd sinks, in addition to the run's labels
+  #[arg(long, value_enum, value_delimiter = ',', default_values_t = [MetricTag::Scenario, MetricTag::Operation, MetricTag::Table, MetricTag::RunId])]
+  metric_tags: Vec<MetricTag>,
+  /// Start a new numbered file once the current metrics file reaches this many megabytes (applies to every file sink)
+  #[arg(long)]
+  file_rotation_size: Option<u64>,
+  /// Start a new numbered file once the current metrics file has been open for this many seconds (applies to every file sink)
+  #[arg(long)]
+  file_rotation_interval: Option<u64>,
+}
+
+impl SinkArgs {
+  /// Whether any sink writes the metrics to stdout
+  pub fn writes_to_stdout(&self) -> bool {
+    self.sinks.contains(&SinkKind::Stdout)
+  }
+
+  fn file_rotation(&self) -> FileRotation {
+    FileRotation {
+      max_bytes: self
+        .file_rotation_size
+        .map(|megabytes| megabytes * 1024 * 1024),
+      max_age: self.file_rotation_interval.map(Duration::from_secs),
+    }
+  }
+
+  fn metric_tags(&self, table_name: &str) -> MetricTags {
+    MetricTags {
+      tags: self.metric_tags.clone(),
+      table_name: table_name.to_owned(),
+    }
+  }
+}
+
+/// Creates every sink selected on the command line, failing fast if any of them can't be initialized
+pub async fn create_sinks(
+  args: &SinkArgs,
+  table_name: &str,
+  run: &RunMetadata,
+  gauges: Arc<RunGauges>,
+) -> anyhow::Result<Vec<Box<dyn MetricsSink>>> {
+  let mut sinks: Vec<Box<dyn MetricsSink>> = Vec::new();
+  for sink in &args.sinks {
+    match sink {
+      SinkKind::Elasticsearch => sinks.push(Box::new(
+        ElasticsearchSink::new(
+          &args.elasticsearch_url,
+          args.username.clone(),
+          args.password.clone(),
+          args.index.clone(),
+          args.elasticsearch_mode,
+          args.aggregation_window,
+        )
+        .await?,
+      )),
+      SinkKind::JsonLines => sinks.push(Box::new(FileSink::<JsonLinesWriter>::new(
+        &args.json_lines_file,
+        args.file_rotation(),
+      )?)),
+      SinkKind::Csv => sinks.push(Box::new(FileSink::<CsvWriter>::new(
+        &args.csv_file,
+        args.file_rotation(),
+      )?)),
+      SinkKind::Parquet => sinks.push(Box::new(FileSink::<ParquetWriter>::new(
+        &args.parquet_file,
+        args.file_rotation(),
+      )?)),
+      SinkKind::Prometheus => sinks.push(Box::new(PrometheusSink::new(
+        args.prometheus_address,
+        run,
+        gauges.clone(),
+      )?)),
+      SinkKind::Otlp => sinks.push(Box::new(OtlpSink::new(
+        args.otlp_endpoint.as_deref(),
+        args.otlp_protocol,
+        run,
+      )?)),
+      SinkKind::Influxdb => sinks.push(Box::new(InfluxDbSink::new(
+        &args.influxdb_url,
+        args.influxdb_token.clone(),
+        args.metric_tags(table_name),
+      )?)),
+      SinkKind::Statsd => sinks.push(Box::new(StatsDSink::new(
+        &args.statsd_address,
+        args.metric_tags(table_name),
+      )?)),
+      SinkKind::Stdout => sinks.push(Box::new(StdoutSink)),
+      SinkKind::None => (),
+    }
+  }
+
+  Ok(sinks)
+}
+
+/// Publishes the run to every sink, then every metric received on the channel until all senders have been dropped
+#[tokio::main]
+pub async fn start_metrics_publisher(
+  mut metrics_rx: Receiver<DynamoDbSimulationMetrics>,
+  mut sinks: Vec<Box<dyn MetricsSink>>,
+  run: Arc<RunMetadata>,
+  gauges: Arc<RunGauges>,
+) {
+  for sink in sinks.iter_mut() {
+    if let Err(e) = sink.publish_run(&run).await {
+      error!("Unable to publish the run to {}! {e:?}", sink.name());
+    }
+  }
+
+  while let Some(metrics) = metrics_rx.recv().await {
+    gauges
+      .queued_metrics
+      .store(metrics_rx.len(), Ordering::Relaxed);
+    for sink in sinks.iter_mut() {
+      if let Err(e) = sink.publish(&metrics).await {
+        error!("Unable to publish metrics to {}! {e:?}", sink.name());
+      }
+    }
+  }
+
+  for sink in sinks.iter_mut() {
+    match sink.close().await {
+      Ok(_) => info!("Closed the {} metrics sink", sink.name()),
+      Err(e) => error!("Unable to close the {} metrics sink! {e:?}", sink.name()),
+    }
+  }
+}
@@ -0,0 +1,201 @@
+use async_trait::async_trait;
+use clap::ValueEnum;
+use log::info;
+use opentelemetry::{
+  global,
+  metrics::{Counter, Histogram, MeterProvider},
+  KeyValue,
+};
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{metrics::SdkMeterProvider, trace::SdkTracerProvider, Resource};
+use serde::Serialize;
+
+use crate::{
+  histograms::Phase,
+  models::{run::RunMetadata, serialized_name, DynamoDbSimulationMetrics},
+  telemetry::INSTRUMENTATION_NAME,
+};
+
+use super::MetricsSink;
+
+#[derive(ValueEnum, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum OtlpProtocol {
+  Grpc,
+  Http,
+}
+
+/// Exports a span for every simulation (with child spans for each DynamoDB call and confirmation loop), along with
+/// latency metrics, to an OpenTelemetry collector over OTLP
+pub struct OtlpSink {
+  tracer_provider: SdkTracerProvider,
+  meter_provider: SdkMeterProvider,
+  simulations: Counter<u64>,
+  consumed_capacity: Counter<f64>,
+  retries: Counter<u64>,
+  attempt_latencies: Histogram<f64>,
+  latencies: Histogram<f64>,
+}
+
+impl OtlpSink {
+  /// Installs the global tracer used by the simulators, so it must be created before any simulations start
+  pub fn new(
+    endpoint: Option<&str>,
+    protocol: OtlpProtocol,
+    run: &RunMetadata,
+  ) -> anyhow::Result<OtlpSink> {
+    let (span_exporter, metric_exporter) = match protocol {
+      OtlpProtocol::Grpc => {
+        let mut span_exporter = SpanExporter::builder().with_tonic();
+        let mut metric_exporter = MetricExporter::builder().with_tonic();
+        if let Some(endpoint) = endpoint {
+          span_exporter = span_exporter.with_endpoint(endpoint);
+          metric_exporter = metric_exporter.with_endpoint(endpoint);
+        }
+        (span_exporter.build()?, metric_exporter.build()?)
+      }
+      OtlpProtocol::Http => {
+        // Unlike gRPC, each signal is sent to its own path when using HTTP
+        let mut span_exporter = SpanExporter::builder().with_http();
+        let mut metric_exporter = MetricExporter::builder().with_http();
+        if let Some(endpoint) = endpoint.map(|endpoint| endpoint.trim_end_matches('/')) {
+          span_exporter = span_exporter.with_endpoint(format!("{endpoint}/v1/traces"));
+          metric_exporter = metric_exporter.with_endpoint(format!("{endpoint}/v1/metrics"));
+        }
+        (span_exporter.build()?, metric_exporter.build()?)
+      }
+    };
+
+    let resource = Resource::builder()
+      .with_service_name(INSTRUMENTATION_NAME)
+      .with_attributes([
+        KeyValue::new("service.version", run.version),
+        KeyValue::new("host.name", run.hostname.clone()),
+        KeyValue::new("benchmark.run_id", run.run_id.clone()),
+      ])
+      .with_attributes(
+        run
+          .labels
+          .iter()
+          .map(|(key, value)| KeyValue::new(key.clone(), value.clone())),
+      )
+      .build();
+    let tracer_provider = SdkTracerProvider::builder()
+      .with_batch_exporter(span_exporter)
+      .with_resource(resource.clone())
+      .build();
+    let meter_provider = SdkMeterProvider::builder()
+      .with_periodic_exporter(metric_exporter)
+      .with_resource(resource)
+      .build();
+    global::set_tracer_provider(tracer_provider.clone());
+
+    let meter = meter_provider.meter(INSTRUMENTATION_NAME);
+    let simulations = meter
+      .u64_counter("dynamodb_benchmarker.simulations")
+      .with_description("The number of simulations run")
+      .build();
+    let consumed_capacity = meter
+      .f64_counter("dynamodb_benchmarker.consumed_capacity")
+      .with_description("The read and write capacity units consumed by the simulations")
+      .with_unit("{capacity_unit}")
+      .build();
+    let boundaries = vec![
+      0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0,
+      10000.0,
+    ];
+    let retries = meter
+      .u64_counter("dynamodb_benchmarker.sdk_retries")
+      .with_description("The number of DynamoDB requests the AWS SDK retried")
+      .build();
+    let attempt_latencies = meter
+      .f64_histogram("dynamodb_benchmarker.attempt_latency")
+      .with_description("The latency of each attempt the AWS SDK made to send a DynamoDB request")
+      .with_unit("ms")
+      .with_boundaries(boundaries.clone())
+      .build();
+    let latencies = meter
+      .f64_histogram("dynamodb_benchmarker.latency")
+      .with_description("The latency of each phase of a simulation")
+      .with_unit("ms")
+      .with_boundaries(boundaries)
+      .build();
+
+    info!(
+      "Exporting spans and metrics over OTLP ({protocol:?}) to {}",
+      endpoint.unwrap_or("the default collector endpoint")
+    );
+
+    Ok(OtlpSink {
+      tracer_provider,
+      meter_provider,
+      simulations,
+      consumed_capacity,
+      retries,
+      attempt_latencies,
+      latencies,
+    })
+  }
+}
+
+#[async_trait]
+impl MetricsSink for OtlpSink {
+  fn name(&self) -> &str {
+    "OTLP"
+  }
+
+  async fn publish(&mut self, metrics: &DynamoDbSimulationMetrics) -> anyhow::Result<()> {
+    let attributes = [
+      KeyValue::new("operation", serialized_name(&metrics.operation)),
+      KeyValue::new("scenario", serialized_name(&metrics.scenario)),
+      KeyValue::new(
+        "outcome",
+        if metrics.successful {
+          "success"
+        } else {
+          "failure"
+        },
+      ),
+    ];
+
+    self.simulations.add(1, &attributes);
+    for (kind, capacity_units) in [
+      ("read", metrics.read_capacity_units),
+      ("write", metrics.write_capacity_units),
+    ] {
+      let mut attributes = attributes.to_vec();
+      attributes.push(KeyValue::new("type", kind));
+      self.consumed_capacity.add(capacity_units, &attributes);
+    }
+    for attempt in &metrics.request_attempts {
+      if let Some(latency) = attempt.latency.as_f64() {
+        let mut attributes = attributes.to_vec();
+        attributes.push(KeyValue::new("request", attempt.request));
+        self.attempt_latencies.record(latency / 1000.0, &attributes);
+      }
+    }
+    for (attempt, reason) in metrics.retries() {
+      let mut attributes = attributes.to_vec();
+      attributes.push(KeyValue::new("request", attempt.request));
+      attributes.push(KeyValue::new("reason", reason.to_owned()));
+      self.retries.add(1, &attributes);
+    }
+    for (phase, latency) in Phase::latencies(metrics) {
+      if let Some(latency) = latency.as_f64() {
+        let mut attributes = attributes.to_vec();
+        attributes.push(KeyValue::new("phase", serialized_name(&phase)));
+        // Latencies are recorded in microseconds
+        self.latencies.record(latency / 1000.0, &attributes);
+      }
+    }
+
+    Ok(())
+  }
+
+  async fn close(&mut self) -> anyhow::Result<()> {
+    // Every simulation has finished by the time the sinks are closed, so this flushes every span
+    self.tracer_provider.shutdown()?;
+    self.meter_provider.shutdown()?;
+    Ok(())
+  }
+}
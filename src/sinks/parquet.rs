@@ -0,0 +1,157 @@
+use std::{fs::File, path::Path, sync::Arc};
+
+use arrow_json::reader::{Decoder, ReaderBuilder};
+use arrow_schema::{DataType, Field, Fields, Schema, TimeUnit};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+
+use crate::models::DynamoDbSimulationMetrics;
+
+use super::file::MetricsFileWriter;
+
+/// The number of metrics buffered in memory before they're handed to the Parquet writer as a single record batch
+const ROWS_PER_BATCH: usize = 8192;
+
+/// Writes metrics as Snappy-compressed Parquet, with one column per metric field
+pub struct ParquetWriter {
+  decoder: Decoder,
+  writer: ArrowWriter<File>,
+}
+
+impl ParquetWriter {
+  fn schema() -> Schema {
+    let latency = |name: &str| Field::new(name, DataType::Float64, true);
+    let request_attempt = Fields::from(vec![
+      Field::new("request", DataType::Utf8, false),
+      Field::new("attempt", DataType::UInt32, false),
+      Field::new("latency", DataType::Float64, false),
+      Field::new("status", DataType::UInt16, true),
+      Field::new("failureReason", DataType::Utf8, true),
+    ]);
+    let step = Fields::from(vec![
+      Field::new("phase", DataType::Utf8, false),
+      Field::new("request", DataType::Utf8, false),
+      Field::new("attempt", DataType::UInt32, false),
+      Field::new("latency", DataType::Float64, false),
+      Field::new("outcome", DataType::Utf8, false),
+    ]);
+    let confirmation = Fields::from(vec![
+      Field::new("phase", DataType::Utf8, false),
+      Field::new("attempts", DataType::UInt32, false),
+      Field::new("exhausted", DataType::Boolean, false),
+      Field::new("latency", DataType::Float64, false),
+    ]);
+    let labels = Fields::from(vec![
+      Field::new("keys", DataType::Utf8, false),
+      Field::new("values", DataType::Utf8, true),
+    ]);
+    Schema::new(vec![
+      Field::new("runId", DataType::Utf8, false),
+      Field::new(
+        "labels",
+        DataType::Map(
+          Arc::new(Field::new("entries", DataType::Struct(labels), false)),
+          false,
+        ),
+        false,
+      ),
+      Field::new("operation", DataType::Utf8, false),
+      Field::new(
+        "timestamp",
+        DataType::Timestamp(TimeUnit::Microsecond, Some("+00:00".into())),
+        false,
+      ),
+      Field::new("successful", DataType::Boolean, false),
+      Field::new("scenario", DataType::Utf8, false),
+      Field::new("timeUnit", DataType::Utf8, false),
+      latency("simulationTime"),
+      latency("correctedSimulationTime"),
+      latency("readTime"),
+      latency("writeTime"),
+      latency("writeItemConfirmationTime"),
+      latency("updateTime"),
+      latency("updateItemConfirmationTime"),
+      latency("deleteTime"),
+      latency("deleteItemConfirmationTime"),
+      Field::new(
+        "steps",
+        DataType::List(Arc::new(Field::new("item", DataType::Struct(step), false))),
+        false,
+      ),
+      Field::new("confirmationAttempts", DataType::UInt32, false),
+      Field::new(
+        "confirmations",
+        DataType::List(Arc::new(Field::new(
+          "item",
+          DataType::Struct(confirmation),
+          false,
+        ))),
+        false,
+      ),
+      Field::new("readCapacityUnits", DataType::Float64, false),
+      Field::new("writeCapacityUnits", DataType::Float64, false),
+      Field::new("sdkAttempts", DataType::UInt32, false),
+      Field::new("sdkRetries", DataType::UInt32, false),
+      Field::new(
+        "requestAttempts",
+        DataType::List(Arc::new(Field::new(
+          "item",
+          DataType::Struct(request_attempt),
+          false,
+        ))),
+        false,
+      ),
+      Field::new("errorCategory", DataType::Utf8, true),
+      Field::new("errorCode", DataType::Utf8, true),
+      Field::new("requestId", DataType::Utf8, true),
+    ])
+  }
+
+  /// Hands the buffered metrics to the Parquet writer
+  fn flush_rows(&mut self) -> anyhow::Result<()> {
+    if let Some(batch) = self.decoder.flush()? {
+      self.writer.write(&batch)?;
+    }
+
+    Ok(())
+  }
+}
+
+impl MetricsFileWriter for ParquetWriter {
+  const FORMAT: &'static str = "Parquet";
+
+  fn create(path: &Path) -> anyhow::Result<ParquetWriter> {
+    let schema = Arc::new(ParquetWriter::schema());
+    let properties = WriterProperties::builder()
+      .set_compression(Compression::SNAPPY)
+      .build();
+
+    Ok(ParquetWriter {
+      // A field missing from the schema fails the write, rather than being silently dropped from the file
+      decoder: ReaderBuilder::new(schema.clone())
+        .with_batch_size(ROWS_PER_BATCH)
+        .with_strict_mode(true)
+        .build_decoder()?,
+      writer: ArrowWriter::try_new(File::create(path)?, schema, Some(properties))?,
+    })
+  }
+
+  fn write(&mut self, metrics: &DynamoDbSimulationMetrics) -> anyhow::Result<()> {
+    // The metrics are decoded from their JSON form so the column layout always matches what the other sinks publish
+    self.decoder.decode(&serde_json::to_vec(metrics)?)?;
+    if self.decoder.len() >= ROWS_PER_BATCH {
+      self.flush_rows()?;
+    }
+
+    Ok(())
+  }
+
+  fn bytes_written(&self) -> u64 {
+    (self.writer.bytes_written() + self.writer.in_progress_size()) as u64
+  }
+
+  fn finish(mut self) -> anyhow::Result<()> {
+    self.flush_rows()?;
+    self.writer.close()?;
+    Ok(())
+  }
+}
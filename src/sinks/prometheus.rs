@@ -0,0 +1,257 @@
+use std::{
+  collections::HashMap,
+  convert::Infallible,
+  net::SocketAddr,
+  sync::{atomic::Ordering, Arc},
+};
+
+use async_trait::async_trait;
+use hyper::{
+  header::CONTENT_TYPE,
+  service::{make_service_fn, service_fn},
+  Body, Method, Request, Response, Server, StatusCode,
+};
+use log::{error, info};
+use prometheus::{
+  exponential_buckets, CounterVec, Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge,
+  IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+use crate::{
+  histograms::Phase,
+  models::{run::RunMetadata, serialized_name, DynamoDbSimulationMetrics},
+};
+
+use super::{MetricsSink, RunGauges};
+
+const NAMESPACE: &str = "dynamodb_benchmarker";
+
+/// Aggregates every metric into Prometheus counters and histograms, which are served from an HTTP `/metrics`
+/// endpoint for the lifetime of the benchmarker. Every series carries the run ID and labels as constant labels
+pub struct PrometheusSink {
+  simulations: IntCounterVec,
+  errors: IntCounterVec,
+  consumed_capacity: CounterVec,
+  retries: IntCounterVec,
+  attempt_latencies: HistogramVec,
+  latencies: HistogramVec,
+}
+
+impl PrometheusSink {
+  pub fn new(
+    listen_address: SocketAddr,
+    run: &RunMetadata,
+    gauges: Arc<RunGauges>,
+  ) -> anyhow::Result<PrometheusSink> {
+    let mut const_labels = run
+      .labels
+      .clone()
+      .into_iter()
+      .collect::<HashMap<String, String>>();
+    const_labels.insert(String::from("run_id"), run.run_id.clone());
+    let registry = Registry::new_custom(None, Some(const_labels))?;
+    let simulations = IntCounterVec::new(
+      Opts::new("simulations_total", "The number of simulations run").namespace(NAMESPACE),
+      &["operation", "scenario", "outcome"],
+    )?;
+    let errors = IntCounterVec::new(
+      Opts::new(
+        "errors_total",
+        "The number of simulations that failed or exhausted a confirmation, by error category",
+      )
+      .namespace(NAMESPACE),
+      &["operation", "scenario", "category"],
+    )?;
+    let consumed_capacity = CounterVec::new(
+      Opts::new(
+        "consumed_capacity_units_total",
+        "The read and write capacity units consumed by the simulations",
+      )
+      .namespace(NAMESPACE),
+      &["operation", "scenario", "type"],
+    )?;
+    let retries = IntCounterVec::new(
+      Opts::new(
+        "sdk_retries_total",
+        "The number of DynamoDB requests the AWS SDK retried, by the reason the previous attempt failed",
+      )
+      .namespace(NAMESPACE),
+      &["operation", "scenario", "request", "reason"],
+    )?;
+    let attempt_latencies = HistogramVec::new(
+      HistogramOpts::new(
+        "attempt_latency_milliseconds",
+        "The latency of each attempt the AWS SDK made to send a DynamoDB request",
+      )
+      .namespace(NAMESPACE)
+      .buckets(exponential_buckets(0.1, 2.0, 20)?),
+      &["operation", "scenario", "request"],
+    )?;
+    let latencies = HistogramVec::new(
+      HistogramOpts::new(
+        "latency_milliseconds",
+        "The latency of each phase of a simulation",
+      )
+      .namespace(NAMESPACE)
+      .buckets(exponential_buckets(0.1, 2.0, 20)?),
+      &["operation", "scenario", "outcome", "phase"],
+    )?;
+    let active_simulations = IntGauge::with_opts(
+      Opts::new(
+        "active_simulations",
+        "The number of simulation tasks currently running",
+      )
+      .namespace(NAMESPACE),
+    )?;
+    let metrics_channel_depth = IntGauge::with_opts(
+      Opts::new(
+        "metrics_channel_depth",
+        "The number of metrics waiting in the channel to be published",
+      )
+      .namespace(NAMESPACE),
+    )?;
+
+    let run_info = IntGaugeVec::new(
+      Opts::new(
+        "run_info",
+        "Always 1; identifies the host and version of the benchmarker that's running",
+      )
+      .namespace(NAMESPACE),
+      &["hostname", "version"],
+    )?;
+    run_info
+      .with_label_values(&[run.hostname.as_str(), run.version])
+      .set(1);
+
+    registry.register(Box::new(run_info))?;
+    registry.register(Box::new(simulations.clone()))?;
+    registry.register(Box::new(errors.clone()))?;
+    registry.register(Box::new(consumed_capacity.clone()))?;
+    registry.register(Box::new(retries.clone()))?;
+    registry.register(Box::new(attempt_latencies.clone()))?;
+    registry.register(Box::new(latencies.clone()))?;
+    registry.register(Box::new(active_simulations.clone()))?;
+    registry.register(Box::new(metrics_channel_depth.clone()))?;
+
+    let make_service = make_service_fn(move |_| {
+      let registry = registry.clone();
+      let gauges = gauges.clone();
+      let active_simulations = active_simulations.clone();
+      let metrics_channel_depth = metrics_channel_depth.clone();
+
+      async move {
+        Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+          // The gauges are sampled whenever Prometheus scrapes, so they're current even when no metrics are being published
+          active_simulations.set(gauges.active_simulations.load(Ordering::Relaxed) as i64);
+          metrics_channel_depth.set(gauges.queued_metrics.load(Ordering::Relaxed) as i64);
+
+          let response = serve_metrics(&request, &registry);
+          async move { Ok::<_, Infallible>(response) }
+        }))
+      }
+    });
+
+    let server = Server::try_bind(&listen_address)?.serve(make_service);
+    info!("Serving Prometheus metrics on http://{listen_address}/metrics");
+    tokio::spawn(async move {
+      if let Err(e) = server.await {
+        error!("The Prometheus metrics endpoint shut down unexpectedly! {e:?}");
+      }
+    });
+
+    Ok(PrometheusSink {
+      simulations,
+      errors,
+      consumed_capacity,
+      retries,
+      attempt_latencies,
+      latencies,
+    })
+  }
+}
+
+#[async_trait]
+impl MetricsSink for PrometheusSink {
+  fn name(&self) -> &str {
+    "Prometheus"
+  }
+
+  async fn publish(&mut self, metrics: &DynamoDbSimulationMetrics) -> anyhow::Result<()> {
+    let operation = serialized_name(&metrics.operation);
+    let scenario = serialized_name(&metrics.scenario);
+    let outcome = if metrics.successful {
+      "success"
+    } else {
+      "failure"
+    };
+
+    self
+      .simulations
+      .with_label_values(&[&operation, &scenario, outcome])
+      .inc();
+    if let Some(category) = &metrics.error_category {
+      self
+        .errors
+        .with_label_values(&[&operation, &scenario, &serialized_name(category)])
+        .inc();
+    }
+    self
+      .consumed_capacity
+      .with_label_values(&[&operation, &scenario, "read"])
+      .inc_by(metrics.read_capacity_units);
+    self
+      .consumed_capacity
+      .with_label_values(&[&operation, &scenario, "write"])
+      .inc_by(metrics.write_capacity_units);
+    for attempt in &metrics.request_attempts {
+      if let Some(latency) = attempt.latency.as_f64() {
+        self
+          .attempt_latencies
+          .with_label_values(&[&operation, &scenario, attempt.request])
+          .observe(latency / 1000.0);
+      }
+    }
+    for (attempt, reason) in metrics.retries() {
+      self
+        .retries
+        .with_label_values(&[&operation, &scenario, attempt.request, reason])
+        .inc();
+    }
+    for (phase, latency) in Phase::latencies(metrics) {
+      if let Some(latency) = latency.as_f64() {
+        // Latencies are recorded in microseconds
+        self
+          .latencies
+          .with_label_values(&[&operation, &scenario, outcome, &serialized_name(&phase)])
+          .observe(latency / 1000.0);
+      }
+    }
+
+    Ok(())
+  }
+}
+
+fn serve_metrics(request: &Request<Body>, registry: &Registry) -> Response<Body> {
+  if request.method() != Method::GET || request.uri().path() != "/metrics" {
+    return Response::builder()
+      .status(StatusCode::NOT_FOUND)
+      .body(Body::empty())
+      .unwrap();
+  }
+
+  let encoder = TextEncoder::new();
+  let mut buffer = Vec::new();
+  match encoder.encode(&registry.gather(), &mut buffer) {
+    Ok(_) => Response::builder()
+      .header(CONTENT_TYPE, encoder.format_type())
+      .body(Body::from(buffer))
+      .unwrap(),
+    Err(e) => {
+      error!("Unable to encode the Prometheus metrics! {e:?}");
+      Response::builder()
+        .status(StatusCode::INTERNAL_SERVER_ERROR)
+        .body(Body::empty())
+        .unwrap()
+    }
+  }
+}
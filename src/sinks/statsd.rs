@@ -0,0 +1,187 @@
+use std::net::UdpSocket;
+
+use async_trait::async_trait;
+use log::info;
+
+use crate::{
+  histograms::Phase,
+  models::{serialized_name, DynamoDbSimulationMetrics},
+};
+
+use super::{MetricTags, MetricsSink};
+
+const PREFIX: &str = "dynamodb_benchmarker";
+
+/// Sends every metric to a StatsD agent as counters and timers, with the tags appended in the DogStatsD format
+/// (`|#key:value,...`) that's understood by Datadog, Telegraf and the StatsD exporter
+pub struct StatsDSink {
+  socket: UdpSocket,
+  tags: MetricTags,
+}
+
+impl StatsDSink {
+  pub fn new(address: &str, tags: MetricTags) -> anyhow::Result<StatsDSink> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.connect(address)?;
+
+    info!("Sending metrics to the StatsD agent at {address}");
+    Ok(StatsDSink { socket, tags })
+  }
+
+  /// Every metric of a simulation, one per line, which are sent in a single datagram
+  fn datagram(&self, metrics: &DynamoDbSimulationMetrics) -> String {
+    let tags = self
+      .tags
+      .values(metrics)
+      .into_iter()
+      .map(|(key, value)| format!("{key}:{}", sanitize(&value)))
+      .collect::<Vec<String>>()
+      .join(",");
+    let tags = if tags.is_empty() {
+      String::new()
+    } else {
+      format!("|#{tags}")
+    };
+    let outcome = if metrics.successful {
+      "success"
+    } else {
+      "failure"
+    };
+
+    let mut lines = vec![format!("{PREFIX}.simulations.{outcome}:1|c{tags}")];
+    if let Some(category) = &metrics.error_category {
+      lines.push(format!(
+        "{PREFIX}.errors.{}:1|c{tags}",
+        serialized_name(category)
+      ));
+    }
+    if metrics.confirmation_attempts > 0 {
+      lines.push(format!(
+        "{PREFIX}.confirmation_attempts:{}|c{tags}",
+        metrics.confirmation_attempts
+      ));
+    }
+    let exhausted_confirmations = metrics.exhausted_confirmations();
+    if exhausted_confirmations > 0 {
+      lines.push(format!(
+        "{PREFIX}.exhausted_confirmations:{exhausted_confirmations}|c{tags}"
+      ));
+    }
+    // Capacity units are fractional, which DogStatsD and the StatsD exporter both accept for counters
+    lines.push(format!(
+      "{PREFIX}.read_capacity_units:{}|c{tags}",
+      metrics.read_capacity_units
+    ));
+    lines.push(format!(
+      "{PREFIX}.write_capacity_units:{}|c{tags}",
+      metrics.write_capacity_units
+    ));
+    for attempt in &metrics.request_attempts {
+      if let Some(latency) = attempt.latency.as_f64() {
+        lines.push(format!(
+          "{PREFIX}.attempt_latency.{}:{}|ms{tags}",
+          attempt.request,
+          latency / 1000.0
+        ));
+      }
+    }
+    for (_, reason) in metrics.retries() {
+      lines.push(format!("{PREFIX}.sdk_retries.{reason}:1|c{tags}"));
+    }
+    for (phase, latency) in Phase::latencies(metrics) {
+      // StatsD timers are always in milliseconds, but fractional values keep the microsecond precision
+      if let Some(latency) = latency.as_f64() {
+        lines.push(format!(
+          "{PREFIX}.latency.{}:{}|ms{tags}",
+          serialized_name(&phase),
+          latency / 1000.0
+        ));
+      }
+    }
+
+    lines.join("\n")
+  }
+}
+
+#[async_trait]
+impl MetricsSink for StatsDSink {
+  fn name(&self) -> &str {
+    "StatsD"
+  }
+
+  async fn publish(&mut self, metrics: &DynamoDbSimulationMetrics) -> anyhow::Result<()> {
+    self.socket.send(self.datagram(metrics).as_bytes())?;
+    Ok(())
+  }
+}
+
+/// Replaces the characters that separate tags (`,`), fields (`|`), the tag section (`#`) and metrics (newlines) in a
+/// DogStatsD datagram, so a tag value can't break it
+fn sanitize(value: &str) -> String {
+  value.replace([',', '|', '#', '\n'], "_")
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use serde_json::Number;
+
+  use super::*;
+  use crate::{models::DynamoOperation, sinks::MetricTag};
+
+  fn sink(address: &str) -> StatsDSink {
+    StatsDSink::new(
+      address,
+      MetricTags {
+        tags: vec![MetricTag::Operation],
+        table_name: String::from("table"),
+      },
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn datagram_sanitizes_tag_values() {
+    let datagram = sink("127.0.0.1:8125").datagram(&DynamoDbSimulationMetrics {
+      operation: DynamoOperation::Read,
+      successful: true,
+      labels: [(String::from("env"), String::from("a,b|c#d"))].into(),
+      read_capacity_units: 0.5,
+      simulation_time: Some(Number::from(1500)),
+      ..DynamoDbSimulationMetrics::default()
+    });
+
+    assert_eq!(
+      datagram.lines().collect::<Vec<&str>>(),
+      [
+        "dynamodb_benchmarker.simulations.success:1|c|#operation:read,env:a_b_c_d",
+        "dynamodb_benchmarker.read_capacity_units:0.5|c|#operation:read,env:a_b_c_d",
+        "dynamodb_benchmarker.write_capacity_units:0|c|#operation:read,env:a_b_c_d",
+        "dynamodb_benchmarker.latency.simulation:1.5|ms|#operation:read,env:a_b_c_d",
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn publish_sends_the_datagram_to_the_agent() {
+    let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    agent
+      .set_read_timeout(Some(Duration::from_secs(5)))
+      .unwrap();
+    let mut sink = sink(&agent.local_addr().unwrap().to_string());
+    let metrics = DynamoDbSimulationMetrics {
+      operation: DynamoOperation::Write,
+      ..DynamoDbSimulationMetrics::default()
+    };
+
+    sink.publish(&metrics).await.unwrap();
+
+    let mut buffer = [0; 4096];
+    let received = agent.recv(&mut buffer).unwrap();
+    assert_eq!(
+      std::str::from_utf8(&buffer[..received]).unwrap(),
+      sink.datagram(&metrics)
+    );
+  }
+}
@@ -0,0 +1,25 @@
+use async_trait::async_trait;
+
+use crate::models::{run::RunMetadata, DynamoDbSimulationMetrics};
+
+use super::MetricsSink;
+
+/// Prints the run, followed by every metric, to stdout as a single line of JSON each
+pub struct StdoutSink;
+
+#[async_trait]
+impl MetricsSink for StdoutSink {
+  fn name(&self) -> &str {
+    "stdout"
+  }
+
+  async fn publish_run(&mut self, run: &RunMetadata) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string(run)?);
+    Ok(())
+  }
+
+  async fn publish(&mut self, metrics: &DynamoDbSimulationMetrics) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string(metrics)?);
+    Ok(())
+  }
+}
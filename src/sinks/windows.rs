@@ -0,0 +1,361 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+
+use crate::{
+  histograms::{LatencyHistograms, LatencyPercentiles},
+  models::{
+    errors::ErrorCategory, serialized_name, DynamoDbSimulationMetrics, DynamoOperation, Scenario,
+    TimeUnit,
+  },
+};
+
+/// The latency percentiles of one phase of an operation's simulations within a window
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowLatencies {
+  pub count: u64,
+  pub min: u64,
+  pub mean: f64,
+  pub p50: u64,
+  pub p90: u64,
+  pub p99: u64,
+  pub p999: u64,
+  pub max: u64,
+}
+
+impl From<&LatencyPercentiles> for WindowLatencies {
+  fn from(percentiles: &LatencyPercentiles) -> WindowLatencies {
+    WindowLatencies {
+      count: percentiles.count,
+      min: percentiles.min,
+      mean: percentiles.mean,
+      p50: percentiles.p50,
+      p90: percentiles.p90,
+      p99: percentiles.p99,
+      p999: percentiles.p999,
+      max: percentiles.max,
+    }
+  }
+}
+
+/// The totals of an operation's simulations within a window
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct WindowCounts {
+  simulations: u64,
+  successful: u64,
+  failed: u64,
+  errors: BTreeMap<ErrorCategory, u64>,
+  confirmation_attempts: u64,
+  exhausted_confirmations: u64,
+  sdk_retries: u64,
+  read_capacity_units: f64,
+  write_capacity_units: f64,
+}
+
+/// Every simulation of a single operation that finished within a time window, summarized into one document
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowedMetrics {
+  pub run_id: String,
+  pub labels: BTreeMap<String, String>,
+  pub operation: DynamoOperation,
+  pub scenario: Scenario,
+  /// The start of the window
+  pub timestamp: DateTime<Utc>,
+  pub window_seconds: u64,
+  /// The number of simulations that finished in the window, per second of the window the run was running for
+  pub throughput: f64,
+  #[serde(flatten)]
+  counts: WindowCounts,
+  /// The unit of every latency below
+  pub time_unit: TimeUnit,
+  /// The latency percentiles of every phase, keyed by phase, from the merged histograms of the window's simulations
+  pub latencies: BTreeMap<String, WindowLatencies>,
+}
+
+/// The simulations that finished within a single window, across every operation
+#[derive(Default)]
+struct Window {
+  run_id: String,
+  labels: BTreeMap<String, String>,
+  scenario: Scenario,
+  counts: BTreeMap<DynamoOperation, WindowCounts>,
+  histograms: LatencyHistograms,
+}
+
+/// Aggregates metrics into fixed windows by the time each simulation finished. Since simulations finish (and are published)
+/// roughly in order, a window is only closed once a simulation has finished a full window after it ended; the rare metric
+/// that arrives for a window that was already closed is published in a separate document for the same window
+pub struct WindowAggregator {
+  window: TimeDelta,
+  windows: BTreeMap<DateTime<Utc>, Window>,
+  /// When the earliest simulation started, since the run's first window is only partly covered by the run
+  started_at: Option<DateTime<Utc>>,
+  /// When the latest simulation finished, since the run's last window is only partly covered by the run
+  finished_at: Option<DateTime<Utc>>,
+}
+
+impl WindowAggregator {
+  pub fn new(window_seconds: u64) -> WindowAggregator {
+    WindowAggregator {
+      window: TimeDelta::seconds(window_seconds as i64),
+      windows: BTreeMap::new(),
+      started_at: None,
+      finished_at: None,
+    }
+  }
+
+  /// Adds the metrics to their window, returning every window that is now closed
+  pub fn record(&mut self, metrics: &DynamoDbSimulationMetrics) -> Vec<WindowedMetrics> {
+    let finished_at = metrics.timestamp
+      + metrics
+        .simulation_time
+        .as_ref()
+        .and_then(|latency| latency.as_i64())
+        .map_or(TimeDelta::zero(), TimeDelta::microseconds);
+    let window_start = self.window_start(finished_at);
+    self.started_at = Some(self.started_at.map_or(metrics.timestamp, |started_at| {
+      started_at.min(metrics.timestamp)
+    }));
+    self.finished_at = Some(self.finished_at.map_or(finished_at, |last_finished_at| {
+      last_finished_at.max(finished_at)
+    }));
+
+    let window = self.windows.entry(window_start).or_insert_with(|| Window {
+      run_id: metrics.run_id.clone(),
+      labels: metrics.labels.clone(),
+      scenario: metrics.scenario,
+      ..Window::default()
+    });
+    window.histograms.record_metrics(metrics);
+    let counts = window.counts.entry(metrics.operation).or_default();
+    counts.simulations += 1;
+    if metrics.successful {
+      counts.successful += 1;
+    } else {
+      counts.failed += 1;
+    }
+    if let Some(category) = metrics.error_category {
+      *counts.errors.entry(category).or_default() += 1;
+    }
+    counts.confirmation_attempts += metrics.confirmation_attempts as u64;
+    counts.exhausted_confirmations += metrics.exhausted_confirmations() as u64;
+    counts.sdk_retries += metrics.sdk_retries as u64;
+    counts.read_capacity_units += metrics.read_capacity_units;
+    counts.write_capacity_units += metrics.write_capacity_units;
+
+    let open_windows = self.windows.split_off(&(finished_at - self.window * 2));
+    let closed_windows = std::mem::replace(&mut self.windows, open_windows);
+    // A closed window ended at least a full window before the latest simulation finished, so the run covered all of its end
+    self.summarize(closed_windows, None)
+  }
+
+  /// Closes every remaining window, once the run has finished
+  pub fn finish(&mut self) -> Vec<WindowedMetrics> {
+    let windows = std::mem::take(&mut self.windows);
+    self.summarize(windows, self.finished_at)
+  }
+
+  fn window_start(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+    let window_micros = self.window.num_microseconds().unwrap_or(1).max(1);
+    let start_micros = time.timestamp_micros().div_euclid(window_micros) * window_micros;
+    DateTime::from_timestamp_micros(start_micros).unwrap_or(time)
+  }
+
+  /// The number of seconds of the window the run was running for: the full window, except for the first window (which the
+  /// run started partway through) and the last one (which the run finished partway through)
+  fn covered_seconds(&self, window_start: DateTime<Utc>, run_end: Option<DateTime<Utc>>) -> f64 {
+    let window_end = window_start + self.window;
+    let start = self
+      .started_at
+      .map_or(window_start, |started_at| started_at.max(window_start));
+    let end = run_end.map_or(window_end, |run_end| run_end.min(window_end));
+    let covered_seconds = (end - start).num_microseconds().unwrap_or_default() as f64 / 1_000_000.0;
+    if covered_seconds > 0.0 {
+      covered_seconds
+    } else {
+      self.window.num_seconds() as f64
+    }
+  }
+
+  fn summarize(
+    &self,
+    windows: BTreeMap<DateTime<Utc>, Window>,
+    run_end: Option<DateTime<Utc>>,
+  ) -> Vec<WindowedMetrics> {
+    let window_seconds = self.window.num_seconds() as u64;
+    windows
+      .into_iter()
+      .flat_map(|(timestamp, window)| {
+        let covered_seconds = self.covered_seconds(timestamp, run_end);
+        let percentiles = window.histograms.percentiles();
+        window
+          .counts
+          .into_iter()
+          .map(move |(operation, counts)| WindowedMetrics {
+            run_id: window.run_id.clone(),
+            labels: window.labels.clone(),
+            operation,
+            scenario: window.scenario,
+            timestamp,
+            window_seconds,
+            throughput: counts.simulations as f64 / covered_seconds,
+            counts,
+            time_unit: TimeUnit::default(),
+            latencies: percentiles
+              .iter()
+              .filter(|percentiles| percentiles.operation == operation)
+              .map(|percentiles| {
+                (
+                  serialized_name(&percentiles.phase),
+                  WindowLatencies::from(percentiles),
+                )
+              })
+              .collect(),
+          })
+          .collect::<Vec<_>>()
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::Number;
+
+  use super::*;
+
+  /// The start of a window, for simulations that start the given number of seconds after it
+  fn start() -> DateTime<Utc> {
+    DateTime::from_timestamp(1_000_000_000, 0).unwrap()
+  }
+
+  fn metrics(
+    operation: DynamoOperation,
+    started_after: f64,
+    finished_after: f64,
+  ) -> DynamoDbSimulationMetrics {
+    let started_at = start() + TimeDelta::microseconds((started_after * 1_000_000.0) as i64);
+    DynamoDbSimulationMetrics {
+      operation,
+      timestamp: started_at,
+      successful: true,
+      simulation_time: Some(Number::from(
+        ((finished_after - started_after) * 1_000_000.0) as u64,
+      )),
+      ..DynamoDbSimulationMetrics::default()
+    }
+  }
+
+  fn summary(windows: &[WindowedMetrics]) -> Vec<(i64, DynamoOperation, u64)> {
+    windows
+      .iter()
+      .map(|window| {
+        (
+          (window.timestamp - start()).num_seconds(),
+          window.operation,
+          window.counts.simulations,
+        )
+      })
+      .collect()
+  }
+
+  #[test]
+  fn windows_close_once_a_simulation_finishes_a_full_window_after_them() {
+    let mut aggregator = WindowAggregator::new(10);
+
+    assert!(aggregator
+      .record(&metrics(DynamoOperation::Read, 0.0, 1.0))
+      .is_empty());
+    assert!(aggregator
+      .record(&metrics(DynamoOperation::Write, 8.0, 9.0))
+      .is_empty());
+    assert!(aggregator
+      .record(&metrics(DynamoOperation::Read, 14.0, 15.0))
+      .is_empty());
+    // The first window ended at 10s, so it's only closed once a simulation finishes 20s or more into the run
+    assert!(aggregator
+      .record(&metrics(DynamoOperation::Read, 18.0, 19.5))
+      .is_empty());
+    let closed = aggregator.record(&metrics(DynamoOperation::Read, 24.0, 25.0));
+    assert_eq!(
+      summary(&closed),
+      [
+        (0, DynamoOperation::Read, 1),
+        (0, DynamoOperation::Write, 1)
+      ]
+    );
+
+    assert_eq!(
+      summary(&aggregator.finish()),
+      [
+        (10, DynamoOperation::Read, 2),
+        (20, DynamoOperation::Read, 1)
+      ]
+    );
+    assert!(aggregator.finish().is_empty());
+  }
+
+  #[test]
+  fn simulations_are_assigned_to_windows_by_when_they_finished() {
+    let mut aggregator = WindowAggregator::new(10);
+
+    aggregator.record(&metrics(DynamoOperation::Read, 8.0, 12.0));
+
+    let windows = aggregator.finish();
+    assert_eq!(summary(&windows), [(10, DynamoOperation::Read, 1)]);
+    assert_eq!(windows[0].latencies["simulation"].count, 1);
+  }
+
+  #[test]
+  fn late_metrics_for_a_closed_window_are_published_separately() {
+    let mut aggregator = WindowAggregator::new(10);
+
+    aggregator.record(&metrics(DynamoOperation::Read, 0.0, 1.0));
+    let closed = aggregator.record(&metrics(DynamoOperation::Read, 24.0, 25.0));
+    assert_eq!(summary(&closed), [(0, DynamoOperation::Read, 1)]);
+
+    assert!(aggregator
+      .record(&metrics(DynamoOperation::Read, 2.0, 3.0))
+      .is_empty());
+    let closed = aggregator.record(&metrics(DynamoOperation::Read, 25.0, 26.0));
+    assert_eq!(summary(&closed), [(0, DynamoOperation::Read, 1)]);
+    assert_eq!(
+      summary(&aggregator.finish()),
+      [(20, DynamoOperation::Read, 2)]
+    );
+  }
+
+  #[test]
+  fn throughput_only_counts_the_part_of_a_window_the_run_covered() {
+    let mut aggregator = WindowAggregator::new(10);
+
+    // The run starts 5s into the first window and finishes 3s into the last one
+    aggregator.record(&metrics(DynamoOperation::Read, 5.0, 6.0));
+    for finished_after in [12.0, 17.0] {
+      aggregator.record(&metrics(
+        DynamoOperation::Read,
+        finished_after - 1.0,
+        finished_after,
+      ));
+    }
+    let mut windows = aggregator.record(&metrics(DynamoOperation::Read, 20.0, 21.0));
+    for finished_after in [22.0, 23.0] {
+      windows.extend(aggregator.record(&metrics(
+        DynamoOperation::Read,
+        finished_after - 1.0,
+        finished_after,
+      )));
+    }
+    windows.extend(aggregator.finish());
+
+    let throughputs = windows
+      .iter()
+      .map(|window| (window.window_seconds, window.throughput))
+      .collect::<Vec<(u64, f64)>>();
+    assert_eq!(throughputs, [(10, 0.2), (10, 0.2), (10, 1.0)]);
+  }
+}
@@ -0,0 +1,570 @@
+use std::{collections::BTreeMap, fmt, fs, path::Path};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{
+  histograms::{LatencyHistograms, LatencyPercentiles, Phase},
+  models::{
+    errors::{DynamoDbErrorDetails, ErrorCategory},
+    run::RunMetadata,
+    DynamoDbSimulationMetrics, DynamoOperation, Scenario, StepOutcome, TimeUnit,
+  },
+};
+
+#[derive(Serialize, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationCounts {
+  pub total: u64,
+  pub successful: u64,
+  pub failed: u64,
+}
+
+impl OperationCounts {
+  pub fn merge(&mut self, other: &OperationCounts) {
+    self.total += other.total;
+    self.successful += other.successful;
+    self.failed += other.failed;
+  }
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmationStats {
+  /// The number of simulations that had to confirm at least one write, update, or delete
+  pub simulations: u64,
+  pub total_attempts: u64,
+  pub max_attempts: u32,
+  pub exhausted: u64,
+  /// The number of exhausted confirmations in each confirmation phase
+  pub exhausted_by_phase: BTreeMap<Phase, u64>,
+}
+
+impl ConfirmationStats {
+  fn merge(&mut self, other: &ConfirmationStats) {
+    self.simulations += other.simulations;
+    self.total_attempts += other.total_attempts;
+    self.max_attempts = self.max_attempts.max(other.max_attempts);
+    self.exhausted += other.exhausted;
+    for (phase, exhausted) in &other.exhausted_by_phase {
+      *self.exhausted_by_phase.entry(*phase).or_default() += exhausted;
+    }
+  }
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryStats {
+  /// The number of DynamoDB requests sent by the simulations
+  pub requests: u64,
+  /// The number of attempts the AWS SDK made to send them, including retries
+  pub attempts: u64,
+  /// The number of requests that needed more than one attempt
+  pub retried_requests: u64,
+  pub max_attempts: u32,
+  /// Why the attempts that were retried failed
+  pub reasons: BTreeMap<String, u64>,
+}
+
+impl RetryStats {
+  fn merge(&mut self, other: &RetryStats) {
+    self.requests += other.requests;
+    self.attempts += other.attempts;
+    self.retried_requests += other.retried_requests;
+    self.max_attempts = self.max_attempts.max(other.max_attempts);
+    for (reason, count) in &other.reasons {
+      *self.reasons.entry(reason.clone()).or_default() += count;
+    }
+  }
+}
+
+/// The DynamoDB requests made in one phase of a set of simulations
+#[derive(Serialize, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct StepStats {
+  pub requests: u64,
+  pub unconfirmed: u64,
+  pub failed: u64,
+  /// The sum of every request's latency
+  pub total_latency: u64,
+}
+
+impl StepStats {
+  fn merge(&mut self, other: &StepStats) {
+    self.requests += other.requests;
+    self.unconfirmed += other.unconfirmed;
+    self.failed += other.failed;
+    self.total_latency += other.total_latency;
+  }
+}
+
+/// The read and write capacity units consumed by a set of simulations
+#[derive(Serialize, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsumedCapacity {
+  pub read_capacity_units: f64,
+  pub write_capacity_units: f64,
+}
+
+impl ConsumedCapacity {
+  fn merge(&mut self, other: &ConsumedCapacity) {
+    self.read_capacity_units += other.read_capacity_units;
+    self.write_capacity_units += other.write_capacity_units;
+  }
+}
+
+/// Everything a simulation task observed over the course of a run.
+/// Each task records into its own instance, which are merged together when the tasks finish
+#[derive(Default)]
+pub struct SimulationStats {
+  pub latency_histograms: LatencyHistograms,
+  pub operations: BTreeMap<DynamoOperation, OperationCounts>,
+  pub consumed_capacity: BTreeMap<DynamoOperation, ConsumedCapacity>,
+  pub steps: BTreeMap<(DynamoOperation, Phase), StepStats>,
+  pub confirmations: ConfirmationStats,
+  pub retries: RetryStats,
+  pub error_categories: BTreeMap<ErrorCategory, u64>,
+  pub errors: BTreeMap<String, u64>,
+}
+
+/// The key an error is counted under: the DynamoDB error code or, for DynamoDB errors without one, their category, since
+/// the root cause of an SDK error is a generic message such as "service error". Any other error is keyed on its root cause
+fn error_key(metrics: &DynamoDbSimulationMetrics, error: &anyhow::Error) -> String {
+  match (
+    &metrics.error_code,
+    error.downcast_ref::<DynamoDbErrorDetails>(),
+  ) {
+    (Some(code), _) => code.clone(),
+    (None, Some(details)) => format!("{:?}", details.category),
+    (None, None) => error.root_cause().to_string(),
+  }
+}
+
+impl SimulationStats {
+  pub fn record(&mut self, metrics: &DynamoDbSimulationMetrics, error: Option<&anyhow::Error>) {
+    self.latency_histograms.record_metrics(metrics);
+
+    let counts = self.operations.entry(metrics.operation).or_default();
+    counts.total += 1;
+    if metrics.successful {
+      counts.successful += 1;
+    } else {
+      counts.failed += 1;
+    }
+
+    let capacity = self.consumed_capacity.entry(metrics.operation).or_default();
+    capacity.read_capacity_units += metrics.read_capacity_units;
+    capacity.write_capacity_units += metrics.write_capacity_units;
+
+    for step in &metrics.steps {
+      let stats = self
+        .steps
+        .entry((metrics.operation, step.phase))
+        .or_default();
+      stats.requests += 1;
+      match step.outcome {
+        StepOutcome::Succeeded => (),
+        StepOutcome::Unconfirmed => stats.unconfirmed += 1,
+        StepOutcome::Failed => stats.failed += 1,
+      }
+      stats.total_latency += step.latency.as_u64().unwrap_or_default();
+    }
+
+    if metrics.confirmation_attempts > 0 {
+      self.confirmations.simulations += 1;
+      self.confirmations.total_attempts += metrics.confirmation_attempts as u64;
+      self.confirmations.max_attempts = self
+        .confirmations
+        .max_attempts
+        .max(metrics.confirmation_attempts);
+      self.confirmations.exhausted += metrics.exhausted_confirmations() as u64;
+      for confirmation in metrics
+        .confirmations
+        .iter()
+        .filter(|confirmation| confirmation.exhausted)
+      {
+        *self
+          .confirmations
+          .exhausted_by_phase
+          .entry(confirmation.phase)
+          .or_default() += 1;
+      }
+    }
+
+    self.retries.attempts += metrics.sdk_attempts as u64;
+    for attempt in &metrics.request_attempts {
+      match attempt.attempt {
+        1 => self.retries.requests += 1,
+        2 => self.retries.retried_requests += 1,
+        _ => (),
+      }
+      self.retries.max_attempts = self.retries.max_attempts.max(attempt.attempt);
+    }
+    for (_, reason) in metrics.retries() {
+      *self.retries.reasons.entry(reason.to_owned()).or_default() += 1;
+    }
+
+    if let Some(category) = metrics.error_category {
+      *self.error_categories.entry(category).or_default() += 1;
+    }
+    if let Some(error) = error {
+      *self.errors.entry(error_key(metrics, error)).or_default() += 1;
+    }
+  }
+
+  pub fn merge(&mut self, other: &SimulationStats) {
+    self.latency_histograms.merge(&other.latency_histograms);
+    for (operation, counts) in &other.operations {
+      self.operations.entry(*operation).or_default().merge(counts);
+    }
+    for (operation, capacity) in &other.consumed_capacity {
+      self
+        .consumed_capacity
+        .entry(*operation)
+        .or_default()
+        .merge(capacity);
+    }
+    for (key, steps) in &other.steps {
+      self.steps.entry(*key).or_default().merge(steps);
+    }
+    self.confirmations.merge(&other.confirmations);
+    self.retries.merge(&other.retries);
+    for (category, count) in &other.error_categories {
+      *self.error_categories.entry(*category).or_default() += count;
+    }
+    for (error, count) in &other.errors {
+      *self.errors.entry(error.clone()).or_default() += count;
+    }
+  }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationSummary {
+  pub operation: DynamoOperation,
+  #[serde(flatten)]
+  pub counts: OperationCounts,
+  pub throughput_per_second: f64,
+  #[serde(flatten)]
+  pub consumed_capacity: ConsumedCapacity,
+  /// The mean read and write capacity units consumed by each simulation
+  pub capacity_per_simulation: ConsumedCapacity,
+}
+
+/// Where the simulations of an operation spent their time, one phase at a time
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StepSummary {
+  pub operation: DynamoOperation,
+  pub phase: Phase,
+  #[serde(flatten)]
+  pub stats: StepStats,
+  pub mean_requests_per_simulation: f64,
+  pub mean_latency: f64,
+  /// The mean time each simulation spent in this phase
+  pub mean_time_per_simulation: f64,
+  /// The percentage of the operation's total simulation time spent in this phase
+  pub share_of_simulation_time: f64,
+}
+
+/// The consolidated results of a benchmark run
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RunSummary {
+  pub run_id: String,
+  pub hostname: String,
+  pub version: &'static str,
+  pub labels: BTreeMap<String, String>,
+  pub table_name: String,
+  pub scenario: Scenario,
+  pub started_at: DateTime<Utc>,
+  pub ended_at: DateTime<Utc>,
+  pub duration_seconds: f64,
+  #[serde(flatten)]
+  pub totals: OperationCounts,
+  pub throughput_per_second: f64,
+  pub consumed_capacity: ConsumedCapacity,
+  pub operations: Vec<OperationSummary>,
+  /// The unit of every latency percentile
+  pub latency_unit: TimeUnit,
+  pub latencies: Vec<LatencyPercentiles>,
+  pub steps: Vec<StepSummary>,
+  pub confirmations: ConfirmationStats,
+  pub mean_confirmation_attempts: f64,
+  pub retries: RetryStats,
+  pub error_categories: BTreeMap<ErrorCategory, u64>,
+  pub errors: BTreeMap<String, u64>,
+}
+
+impl RunSummary {
+  pub fn new(
+    run: &RunMetadata,
+    table_name: String,
+    scenario: Scenario,
+    started_at: DateTime<Utc>,
+    ended_at: DateTime<Utc>,
+    stats: &SimulationStats,
+  ) -> RunSummary {
+    let duration_seconds = (ended_at - started_at).num_milliseconds() as f64 / 1000.0;
+    let throughput = |count: u64| {
+      if duration_seconds > 0.0 {
+        count as f64 / duration_seconds
+      } else {
+        0.0
+      }
+    };
+
+    let mut totals = OperationCounts::default();
+    let mut consumed_capacity = ConsumedCapacity::default();
+    let operations = stats
+      .operations
+      .iter()
+      .map(|(&operation, counts)| {
+        let capacity = stats
+          .consumed_capacity
+          .get(&operation)
+          .copied()
+          .unwrap_or_default();
+        totals.merge(counts);
+        consumed_capacity.merge(&capacity);
+        OperationSummary {
+          operation,
+          counts: *counts,
+          throughput_per_second: throughput(counts.total),
+          consumed_capacity: capacity,
+          capacity_per_simulation: ConsumedCapacity {
+            read_capacity_units: capacity.read_capacity_units / counts.total.max(1) as f64,
+            write_capacity_units: capacity.write_capacity_units / counts.total.max(1) as f64,
+          },
+        }
+      })
+      .collect();
+    let latencies = stats.latency_histograms.percentiles();
+    let steps = stats
+      .steps
+      .iter()
+      .map(|(&(operation, phase), step)| {
+        let simulations = stats
+          .operations
+          .get(&operation)
+          .map_or(0, |counts| counts.total)
+          .max(1) as f64;
+        let total_simulation_time = latencies
+          .iter()
+          .find(|latency| latency.operation == operation && latency.phase == Phase::Simulation)
+          .map_or(0.0, |latency| latency.mean * latency.count as f64);
+        StepSummary {
+          operation,
+          phase,
+          stats: *step,
+          mean_requests_per_simulation: step.requests as f64 / simulations,
+          mean_latency: step.total_latency as f64 / step.requests.max(1) as f64,
+          mean_time_per_simulation: step.total_latency as f64 / simulations,
+          share_of_simulation_time: if total_simulation_time > 0.0 {
+            step.total_latency as f64 / total_simulation_time * 100.0
+          } else {
+            0.0
+          },
+        }
+      })
+      .collect();
+    let mean_confirmation_attempts = if stats.confirmations.simulations > 0 {
+      stats.confirmations.total_attempts as f64 / stats.confirmations.simulations as f64
+    } else {
+      0.0
+    };
+
+    RunSummary {
+      run_id: run.run_id.clone(),
+      hostname: run.hostname.clone(),
+      version: run.version,
+      labels: run.labels.clone(),
+      table_name,
+      scenario,
+      started_at,
+      ended_at,
+      duration_seconds,
+      totals,
+      throughput_per_second: throughput(totals.total),
+      consumed_capacity,
+      operations,
+      latency_unit: TimeUnit::Microseconds,
+      latencies,
+      steps,
+      confirmations: stats.confirmations.clone(),
+      mean_confirmation_attempts,
+      retries: stats.retries.clone(),
+      error_categories: stats.error_categories.clone(),
+      errors: stats.errors.clone(),
+    }
+  }
+
+  pub fn write_json(&self, path: &Path) -> anyhow::Result<()> {
+    fs::write(path, serde_json::to_string_pretty(self)?)?;
+    Ok(())
+  }
+}
+
+impl fmt::Display for RunSummary {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(
+      f,
+      "Run {} against {} ({:?})",
+      self.run_id, self.table_name, self.scenario
+    )?;
+    if !self.labels.is_empty() {
+      writeln!(
+        f,
+        "Labels: {}",
+        self
+          .labels
+          .iter()
+          .map(|(key, value)| format!("{key}={value}"))
+          .collect::<Vec<String>>()
+          .join(", ")
+      )?;
+    }
+    writeln!(
+      f,
+      "Duration: {:.1}s | Simulations: {} ({} successful, {} failed) | Throughput: {:.2}/s",
+      self.duration_seconds,
+      self.totals.total,
+      self.totals.successful,
+      self.totals.failed,
+      self.throughput_per_second
+    )?;
+    writeln!(
+      f,
+      "Consumed capacity: {:.1} RCU, {:.1} WCU",
+      self.consumed_capacity.read_capacity_units, self.consumed_capacity.write_capacity_units
+    )?;
+
+    writeln!(f)?;
+    writeln!(
+      f,
+      "{:<10} {:>10} {:>10} {:>10} {:>12} {:>12} {:>12} {:>8} {:>8}",
+      "Operation",
+      "Total",
+      "Successful",
+      "Failed",
+      "Throughput/s",
+      "RCU",
+      "WCU",
+      "RCU/op",
+      "WCU/op"
+    )?;
+    for operation in &self.operations {
+      writeln!(
+        f,
+        "{:<10} {:>10} {:>10} {:>10} {:>12.2} {:>12.1} {:>12.1} {:>8.2} {:>8.2}",
+        format!("{:?}", operation.operation),
+        operation.counts.total,
+        operation.counts.successful,
+        operation.counts.failed,
+        operation.throughput_per_second,
+        operation.consumed_capacity.read_capacity_units,
+        operation.consumed_capacity.write_capacity_units,
+        operation.capacity_per_simulation.read_capacity_units,
+        operation.capacity_per_simulation.write_capacity_units
+      )?;
+    }
+
+    writeln!(f)?;
+    writeln!(
+      f,
+      "{:<10} {:<20} {:>9} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+      "Operation", "Phase (ms)", "Count", "Mean", "p50", "p90", "p99", "p99.9", "Max"
+    )?;
+    // The latencies are recorded in microseconds, but are far easier to read in milliseconds
+    let millis = |micros: u64| micros as f64 / 1000.0;
+    for latency in &self.latencies {
+      writeln!(
+        f,
+        "{:<10} {:<20} {:>9} {:>10.3} {:>10.3} {:>10.3} {:>10.3} {:>10.3} {:>10.3}",
+        format!("{:?}", latency.operation),
+        format!("{:?}", latency.phase),
+        latency.count,
+        latency.mean / 1000.0,
+        millis(latency.p50),
+        millis(latency.p90),
+        millis(latency.p99),
+        millis(latency.p999),
+        millis(latency.max)
+      )?;
+    }
+
+    writeln!(f)?;
+    writeln!(
+      f,
+      "{:<10} {:<20} {:>9} {:>9} {:>11} {:>9} {:>10} {:>10} {:>7}",
+      "Operation",
+      "Step (ms)",
+      "Requests",
+      "Per sim",
+      "Unconfirmed",
+      "Failed",
+      "Mean",
+      "Per sim",
+      "Share"
+    )?;
+    for step in &self.steps {
+      writeln!(
+        f,
+        "{:<10} {:<20} {:>9} {:>9.2} {:>11} {:>9} {:>10.3} {:>10.3} {:>6.1}%",
+        format!("{:?}", step.operation),
+        format!("{:?}", step.phase),
+        step.stats.requests,
+        step.mean_requests_per_simulation,
+        step.stats.unconfirmed,
+        step.stats.failed,
+        step.mean_latency / 1000.0,
+        step.mean_time_per_simulation / 1000.0,
+        step.share_of_simulation_time
+      )?;
+    }
+
+    writeln!(f)?;
+    writeln!(
+      f,
+      "Confirmations: {} simulations, {} reads ({:.2} mean, {} max per simulation), {} exhausted",
+      self.confirmations.simulations,
+      self.confirmations.total_attempts,
+      self.mean_confirmation_attempts,
+      self.confirmations.max_attempts,
+      self.confirmations.exhausted
+    )?;
+    for (phase, exhausted) in &self.confirmations.exhausted_by_phase {
+      writeln!(f, "  {phase:?}: {exhausted} exhausted")?;
+    }
+
+    writeln!(
+      f,
+      "SDK retries: {} of {} requests retried ({} attempts, {} max per request)",
+      self.retries.retried_requests,
+      self.retries.requests,
+      self.retries.attempts,
+      self.retries.max_attempts
+    )?;
+    for (reason, count) in &self.retries.reasons {
+      writeln!(f, "  {count:>8} {reason}")?;
+    }
+
+    if !self.error_categories.is_empty() {
+      writeln!(f, "Error categories:")?;
+      for (category, count) in &self.error_categories {
+        writeln!(f, "  {count:>8} {category:?}")?;
+      }
+    }
+
+    if self.errors.is_empty() {
+      writeln!(f, "Errors: none")?;
+    } else {
+      writeln!(f, "Errors:")?;
+      for (error, count) in &self.errors {
+        writeln!(f, "  {count:>8} {error}")?;
+      }
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,88 @@
+use std::{fmt::Display, future::Future};
+
+use opentelemetry::{
+  context::FutureExt,
+  global,
+  trace::{SpanKind, Status, TraceContextExt, Tracer},
+  Context, KeyValue,
+};
+
+use crate::models::{serialized_name, DynamoDbSimulationMetrics};
+
+/// The name spans and metrics are reported under. Spans are only exported when the OTLP sink is enabled;
+/// otherwise the global tracer is a no-op
+pub const INSTRUMENTATION_NAME: &str = "dynamodb-benchmarker";
+
+/// Starts the root span of a single simulation
+pub fn start_simulation_span() -> Context {
+  let span = global::tracer(INSTRUMENTATION_NAME).start("simulation");
+  Context::current_with_span(span)
+}
+
+/// Records the outcome of the simulation on its span and ends it
+pub fn end_simulation_span(
+  cx: &Context,
+  metrics: &DynamoDbSimulationMetrics,
+  error: Option<&anyhow::Error>,
+) {
+  let span = cx.span();
+  span.set_attributes([
+    KeyValue::new("benchmark.operation", serialized_name(&metrics.operation)),
+    KeyValue::new("benchmark.scenario", serialized_name(&metrics.scenario)),
+    KeyValue::new("benchmark.successful", metrics.successful),
+    KeyValue::new(
+      "benchmark.confirmation_attempts",
+      metrics.confirmation_attempts as i64,
+    ),
+  ]);
+  if let Some(category) = &metrics.error_category {
+    span.set_attribute(KeyValue::new("error.type", serialized_name(category)));
+  }
+  if let Some(error) = error {
+    span.set_status(Status::error(error.root_cause().to_string()));
+  }
+  span.end();
+}
+
+/// Runs a DynamoDB call inside a client span that's a child of the current simulation's span
+pub async fn in_dynamodb_span<T, E: Display>(
+  operation: &'static str,
+  table_name: &str,
+  call: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+  in_span(
+    operation,
+    SpanKind::Client,
+    vec![
+      KeyValue::new("db.system.name", "aws.dynamodb"),
+      KeyValue::new("db.operation.name", operation),
+      KeyValue::new("aws.dynamodb.table_names", table_name.to_owned()),
+    ],
+    call,
+  )
+  .await
+}
+
+/// Runs the future inside a new span that's a child of the current span, marking the span as failed if the future errors
+pub async fn in_span<T, E: Display>(
+  name: &'static str,
+  kind: SpanKind,
+  attributes: Vec<KeyValue>,
+  future: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+  let tracer = global::tracer(INSTRUMENTATION_NAME);
+  let span = tracer
+    .span_builder(name)
+    .with_kind(kind)
+    .with_attributes(attributes)
+    .start(&tracer);
+  let cx = Context::current_with_span(span);
+
+  let result = future.with_context(cx.clone()).await;
+  if let Err(e) = &result {
+    cx.span().set_status(Status::error(e.to_string()));
+  }
+  cx.span().end();
+
+  result
+}